@@ -0,0 +1,109 @@
+//! Checks that the macros of `try_or_wrap_s` can be used from a `no_std` crate
+//!
+//! Run it on its own (`cargo test -p try_or_wrap_s_no_std_test`): when built along with the rest
+//! of the workspace, features are unified and `try_or_wrap_s` gets its `std` feature enabled.
+//! This is why it isn't one of the default members of the workspace.
+
+#![no_std]
+
+use core::fmt;
+use try_or_wrap_s::{inner_errors, try_or_wrap, try_or_wrap_fn, try_or_wrap_opt};
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidInputError;
+#[derive(Debug, PartialEq)]
+pub struct DeviceError;
+#[derive(Debug, PartialEq)]
+pub struct ReadOnlyError;
+
+impl fmt::Display for InvalidInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid input")
+    }
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("read-only register")
+    }
+}
+
+impl core::error::Error for InvalidInputError {}
+impl core::error::Error for ReadOnlyError {}
+
+inner_errors! {
+    #[derive(Debug, PartialEq)]
+    pub enum RegisterFailure {
+        InvalidInput(InvalidInputError),
+        ReadOnly(ReadOnlyError),
+    }
+}
+
+pub fn read_register(address: u8) -> Result<Result<u8, InvalidInputError>, DeviceError> {
+    match address {
+        0 => Err(DeviceError),
+        0x80..=0xFF => Ok(Err(InvalidInputError)),
+        _ => Ok(Ok(address * 2)),
+    }
+}
+
+pub fn read_twice(address: u8) -> Result<Result<u8, InvalidInputError>, DeviceError> {
+    let first = try_or_wrap!(read_register(address)?, Ok);
+    let second = try_or_wrap!(read_register(first)?);
+    Ok(Ok(second))
+}
+
+#[try_or_wrap_fn]
+pub fn read_twice_attribute(address: u8) -> Result<Result<u8, InvalidInputError>, DeviceError> {
+    let first = read_register(address)??;
+    Ok(Ok(read_register(first)??))
+}
+
+pub fn checked_double(value: Option<u8>) -> Result<Option<u8>, DeviceError> {
+    let value = try_or_wrap_opt!(value, Ok);
+    Ok(value.checked_mul(2))
+}
+
+pub fn write_register(address: u8) -> Result<Result<(), RegisterFailure>, DeviceError> {
+    let current = try_or_wrap!(read_register(address)?);
+    if current == 0x10 {
+        try_or_wrap!(Err(ReadOnlyError));
+    }
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_or_wrap() {
+        assert_eq!(read_twice(0x10), Ok(Ok(0x40)));
+        assert_eq!(read_twice(0x40), Ok(Err(InvalidInputError)));
+        assert_eq!(read_twice(0), Err(DeviceError));
+        assert_eq!(read_twice_attribute(0x10), Ok(Ok(0x40)));
+        assert_eq!(read_twice_attribute(0x40), Ok(Err(InvalidInputError)));
+        assert_eq!(read_twice_attribute(0), Err(DeviceError));
+    }
+
+    #[test]
+    fn try_or_wrap_opt() {
+        assert_eq!(checked_double(Some(2)), Ok(Some(4)));
+        assert_eq!(checked_double(Some(0x80)), Ok(None));
+        assert_eq!(checked_double(None), Ok(None));
+    }
+
+    #[test]
+    fn inner_errors() {
+        assert_eq!(write_register(0x10), Ok(Ok(())));
+        assert_eq!(
+            write_register(0x08),
+            Ok(Err(RegisterFailure::ReadOnly(ReadOnlyError)))
+        );
+        assert_eq!(
+            write_register(0x80),
+            Ok(Err(RegisterFailure::InvalidInput(InvalidInputError)))
+        );
+        assert_eq!(write_register(0), Err(DeviceError));
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::vec::Vec;
+
+use crate::FromError;
+
+/// Gathers inner errors instead of returning on the first one
+///
+/// Inner errors are recorded with [`try_or_accumulate!`](crate::try_or_accumulate), and
+/// [`finish`](WrapAccumulator::finish) then gives either all of them at once or the recorded
+/// values, typically to `try_or_wrap!`: `try_or_wrap!(acc.finish(values), Ok)` returns
+/// `Ok(Err(errors))` if any error was recorded. Outer errors are still returned immediately
+/// with `?`.
+///
+/// # Example
+/// ```
+/// use try_or_wrap_s::{try_or_accumulate, try_or_wrap, WrapAccumulator};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum FieldError {
+///     EmptyName,
+///     InvalidAge(String),
+/// }
+/// #[derive(Debug, PartialEq)]
+/// struct DatabaseError;
+///
+/// fn validate_name(name: &str) -> Result<Result<String, FieldError>, DatabaseError> {
+///     Ok(if name.is_empty() { Err(FieldError::EmptyName) } else { Ok(name.to_owned()) })
+/// }
+///
+/// fn validate_age(age: &str) -> Result<u8, FieldError> {
+///     age.parse().map_err(|_| FieldError::InvalidAge(age.to_owned()))
+/// }
+///
+/// fn create_user(name: &str, age: &str) -> Result<Result<(String, u8), Vec<FieldError>>, DatabaseError> {
+///     let mut acc = WrapAccumulator::new();
+///     let name = try_or_accumulate!(acc, validate_name(name)?);
+///     let age = try_or_accumulate!(acc, validate_age(age));
+///     let (name, age) = try_or_wrap!(acc.finish((name, age)), Ok);
+///     Ok(Ok((name, age)))
+/// }
+///
+/// assert_eq!(create_user("Ferris", "8"), Ok(Ok(("Ferris".to_owned(), 8))));
+/// assert_eq!(
+///     create_user("", "eight"),
+///     Ok(Err(vec![FieldError::EmptyName, FieldError::InvalidAge("eight".to_owned())])),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapAccumulator<E> {
+    errors: Vec<E>,
+}
+
+impl<E> WrapAccumulator<E> {
+    pub fn new() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    /// Returns the `Ok` value, or records the error (converted through [`FromError`], as
+    /// `try_or_wrap!` does) and returns `None`
+    #[cfg_attr(feature = "track-location", track_caller)]
+    pub fn record<T, E2, M>(&mut self, result: Result<T, E2>) -> Option<T>
+    where
+        E: FromError<E2, M>,
+    {
+        match result {
+            Ok(val) => Some(val),
+            Err(err) => {
+                self.errors.push(E::from_error(err));
+                None
+            }
+        }
+    }
+
+    /// Errors recorded so far
+    pub fn errors(&self) -> &[E] {
+        &self.errors
+    }
+
+    /// Whether no error has been recorded so far
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Err` with all the recorded errors if there is any, `Ok` with the unwrapped `values`
+    /// otherwise
+    ///
+    /// `values` are the `Option`s returned by [`record`](Self::record) (as a tuple if there are
+    /// several of them, `()` if there is none).
+    ///
+    /// # Panics
+    /// If no error was recorded but one of `values` is `None`, i.e. it wasn't returned by `record`
+    /// of this accumulator.
+    pub fn finish<V: RecordedValues>(self, values: V) -> Result<V::Values, Vec<E>> {
+        if self.errors.is_empty() {
+            Ok(values
+                .into_values()
+                .expect("a value is missing but no error was recorded"))
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+impl<E> Default for WrapAccumulator<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Values given to [`WrapAccumulator::finish`]
+///
+/// Implemented for `()`, `Option<T>` and tuples of up to 8 `Option`s.
+pub trait RecordedValues {
+    type Values;
+    /// `Some` with the unwrapped values if all of them are `Some`
+    fn into_values(self) -> Option<Self::Values>;
+}
+
+impl RecordedValues for () {
+    type Values = ();
+    fn into_values(self) -> Option<()> {
+        Some(())
+    }
+}
+
+impl<T> RecordedValues for Option<T> {
+    type Values = T;
+    fn into_values(self) -> Option<T> {
+        self
+    }
+}
+
+macro_rules! impl_recorded_values {
+    ($($name:ident)+) => {
+        impl<$($name),+> RecordedValues for ($(Option<$name>,)+) {
+            type Values = ($($name,)+);
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Option<Self::Values> {
+                let ($($name,)+) = self;
+                Some(($($name?,)+))
+            }
+        }
+    };
+}
+
+impl_recorded_values!(A);
+impl_recorded_values!(A B);
+impl_recorded_values!(A B C);
+impl_recorded_values!(A B C D);
+impl_recorded_values!(A B C D E);
+impl_recorded_values!(A B C D E F);
+impl_recorded_values!(A B C D E F G);
+impl_recorded_values!(A B C D E F G H);
+
+/// Records the error of `expr` in a [`WrapAccumulator`] instead of returning
+///
+/// Evaluates to `Some(val)` if `expr` is `Ok(val)`, and to `None` otherwise.
+/// See [`WrapAccumulator`] for an example.
+#[macro_export]
+macro_rules! try_or_accumulate {
+    ($acc:expr, $expr:expr) => {
+        $crate::WrapAccumulator::record(&mut $acc, $expr)
+    };
+}
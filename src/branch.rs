@@ -0,0 +1,256 @@
+use core::convert::Infallible;
+use core::ops::ControlFlow;
+use core::task::Poll;
+
+/// Types that `try_or_wrap!` can be applied to
+///
+/// This is a stable equivalent of the `Try` trait: [`branch`](TryOrWrap::branch) splits the value
+/// into either the value `try_or_wrap!` evaluates to, or the residual it returns early with. That
+/// residual is then converted through [`FromBreak`] before being given to the wrapper.
+///
+/// # Example
+/// ```
+/// use std::{convert::Infallible, ops::ControlFlow};
+/// use try_or_wrap_s::{try_or_wrap, FromBreak, TryOrWrap};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Validation<T> {
+///     Valid(T),
+///     Invalid(String),
+/// }
+///
+/// impl<T> TryOrWrap for Validation<T> {
+///     type Continue = T;
+///     type Break = Validation<Infallible>;
+///     fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+///         match self {
+///             Validation::Valid(val) => ControlFlow::Continue(val),
+///             Validation::Invalid(reason) => ControlFlow::Break(Validation::Invalid(reason)),
+///         }
+///     }
+/// }
+///
+/// impl<T> FromBreak<Validation<Infallible>> for Validation<T> {
+///     fn from_break(residual: Validation<Infallible>) -> Self {
+///         match residual {
+///             Validation::Valid(never) => match never {},
+///             Validation::Invalid(reason) => Validation::Invalid(reason),
+///         }
+///     }
+/// }
+///
+/// fn validate(input: i32) -> Validation<u32> {
+///     if input >= 0 {
+///         Validation::Valid(input as u32)
+///     } else {
+///         Validation::Invalid(format!("{} is negative", input))
+///     }
+/// }
+///
+/// fn foo(input: i32) -> Result<Validation<u32>, std::io::Error> {
+///     let validated = try_or_wrap!(validate(input), Ok);
+///     Ok(Validation::Valid(validated * 2))
+/// }
+///
+/// assert_eq!(foo(21).unwrap(), Validation::Valid(42));
+/// assert_eq!(foo(-1).unwrap(), Validation::Invalid("-1 is negative".to_owned()));
+/// ```
+pub trait TryOrWrap {
+    /// What `try_or_wrap!` evaluates to when not returning early
+    type Continue;
+    /// What `try_or_wrap!` returns early with, before conversion through [`FromBreak`] and wrapping
+    ///
+    /// This is typically `Self` with the `Continue` type replaced by `Infallible`
+    /// (e.g. `Result<Infallible, E>` for `Result<T, E>`).
+    type Break;
+    /// Decides whether `try_or_wrap!` should continue with a value or return early
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue>;
+}
+
+/// Builds the value given to the wrapper of `try_or_wrap!` from a [`TryOrWrap::Break`]
+///
+/// This is where the conversion of the error happens, through [`FromError`]. `M` is only there
+/// to tell apart the implementations that go through different [`FromError`] implementations,
+/// and can be left to its default by other implementations.
+pub trait FromBreak<B, M = ()> {
+    fn from_break(residual: B) -> Self;
+}
+
+/// Conversion of the errors returned by `try_or_wrap!`
+///
+/// Any `From` conversion is a `FromError` conversion. With the `track-location` (resp.
+/// `backtrace`) feature, `Located<E2>` (resp. `Traced<E2>`) can also be converted from any error
+/// that converts into `E2`, recording where `try_or_wrap!` was invoked. `M` tells these
+/// implementations apart from the `From` one, and is inferred.
+pub trait FromError<E, M = ()> {
+    fn from_error(err: E) -> Self;
+}
+
+impl<E, F> FromError<E> for F
+where
+    F: From<E>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_error(err: E) -> Self {
+        F::from(err)
+    }
+}
+
+/// Residual of a `try_or_wrap!` with a `map_err` argument: the mapped error is given to the
+/// wrapper as is, without another `From` conversion (so that `map_err = Into::into` works)
+#[doc(hidden)]
+pub struct MappedBreak<E>(pub E);
+
+/// Same as [`FromError`] for the output of `map_err`, that is given as is unless wrapped in
+/// `Located` or `Traced`
+#[doc(hidden)]
+pub trait FromMappedError<E, M = ()> {
+    fn from_mapped_error(err: E) -> Self;
+}
+
+impl<E> FromMappedError<E> for E {
+    fn from_mapped_error(err: E) -> Self {
+        err
+    }
+}
+
+impl<T, E, F, M> FromBreak<MappedBreak<E>, M> for Result<T, F>
+where
+    F: FromMappedError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: MappedBreak<E>) -> Self {
+        Err(F::from_mapped_error(residual.0))
+    }
+}
+
+impl<T, E> TryOrWrap for Result<T, E> {
+    type Continue = T;
+    type Break = Result<Infallible, E>;
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+        match self {
+            Ok(val) => ControlFlow::Continue(val),
+            Err(err) => ControlFlow::Break(Err(err)),
+        }
+    }
+}
+
+impl<T, E, F, M> FromBreak<Result<Infallible, E>, M> for Result<T, F>
+where
+    F: FromError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: Result<Infallible, E>) -> Self {
+        match residual {
+            Ok(never) => match never {},
+            Err(err) => Err(F::from_error(err)),
+        }
+    }
+}
+
+impl<T> TryOrWrap for Option<T> {
+    type Continue = T;
+    type Break = Option<Infallible>;
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+        match self {
+            Some(val) => ControlFlow::Continue(val),
+            None => ControlFlow::Break(None),
+        }
+    }
+}
+
+impl<T> FromBreak<Option<Infallible>> for Option<T> {
+    fn from_break(_residual: Option<Infallible>) -> Self {
+        None
+    }
+}
+
+impl<B, C> TryOrWrap for ControlFlow<B, C> {
+    type Continue = C;
+    type Break = ControlFlow<B, Infallible>;
+    // `Self::Break` would be ambiguous with the `ControlFlow::Break` variant
+    fn branch(self) -> ControlFlow<ControlFlow<B, Infallible>, C> {
+        match self {
+            ControlFlow::Continue(val) => ControlFlow::Continue(val),
+            ControlFlow::Break(b) => ControlFlow::Break(ControlFlow::Break(b)),
+        }
+    }
+}
+
+impl<B, C, B2> FromBreak<ControlFlow<B, Infallible>> for ControlFlow<B2, C>
+where
+    B2: From<B>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: ControlFlow<B, Infallible>) -> Self {
+        match residual {
+            ControlFlow::Continue(never) => match never {},
+            ControlFlow::Break(b) => ControlFlow::Break(B2::from(b)),
+        }
+    }
+}
+
+impl<T, E> TryOrWrap for Poll<Result<T, E>> {
+    type Continue = Poll<T>;
+    type Break = Result<Infallible, E>;
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+        match self {
+            Poll::Ready(Ok(val)) => ControlFlow::Continue(Poll::Ready(val)),
+            Poll::Ready(Err(err)) => ControlFlow::Break(Err(err)),
+            Poll::Pending => ControlFlow::Continue(Poll::Pending),
+        }
+    }
+}
+
+impl<T, E, F, M> FromBreak<Result<Infallible, E>, M> for Poll<Result<T, F>>
+where
+    F: FromError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: Result<Infallible, E>) -> Self {
+        Poll::Ready(FromBreak::<_, M>::from_break(residual))
+    }
+}
+
+impl<T, E, F, M> FromBreak<MappedBreak<E>, M> for Poll<Result<T, F>>
+where
+    F: FromMappedError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: MappedBreak<E>) -> Self {
+        Poll::Ready(FromBreak::<_, M>::from_break(residual))
+    }
+}
+
+impl<T, E> TryOrWrap for Poll<Option<Result<T, E>>> {
+    type Continue = Poll<Option<T>>;
+    type Break = Result<Infallible, E>;
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+        match self {
+            Poll::Ready(Some(Ok(val))) => ControlFlow::Continue(Poll::Ready(Some(val))),
+            Poll::Ready(Some(Err(err))) => ControlFlow::Break(Err(err)),
+            Poll::Ready(None) => ControlFlow::Continue(Poll::Ready(None)),
+            Poll::Pending => ControlFlow::Continue(Poll::Pending),
+        }
+    }
+}
+
+impl<T, E, F, M> FromBreak<Result<Infallible, E>, M> for Poll<Option<Result<T, F>>>
+where
+    F: FromError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: Result<Infallible, E>) -> Self {
+        Poll::Ready(Some(FromBreak::<_, M>::from_break(residual)))
+    }
+}
+
+impl<T, E, F, M> FromBreak<MappedBreak<E>, M> for Poll<Option<Result<T, F>>>
+where
+    F: FromMappedError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: MappedBreak<E>) -> Self {
+        Poll::Ready(Some(FromBreak::<_, M>::from_break(residual)))
+    }
+}
@@ -0,0 +1,170 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt;
+
+use crate::FromError;
+
+/// Error with a trail of human-readable context messages
+///
+/// This is what the `context = "..."` and `with_context = || ...` arguments of `try_or_wrap!`
+/// wrap the error in, after converting it through [`FromError`]: the return type has to expect a
+/// `Contextual<E2>`, `E2` implementing `From` of the error (or being a `Located` or `Traced` of
+/// such a type). When the error is already a `Contextual<E>`, its source is converted and the new
+/// message is appended to the existing trail instead of nesting.
+///
+/// # Example
+/// ```
+/// use std::{error::Error, num::ParseIntError};
+/// use try_or_wrap_s::{try_or_wrap, Contextual};
+///
+/// #[derive(Debug)]
+/// struct DatabaseError;
+///
+/// fn parse_age(age: &str) -> Result<Result<u8, Contextual<ParseIntError>>, DatabaseError> {
+///     Ok(Ok(try_or_wrap!(age.parse(), Ok, context = "parsing age")))
+/// }
+///
+/// fn validate_user(name: &str, age: &str) -> Result<Result<(String, u8), Contextual<ParseIntError>>, DatabaseError> {
+///     let age = try_or_wrap!(parse_age(age)?, Ok, with_context = || format!("validating user {}", name));
+///     Ok(Ok((name.to_owned(), age)))
+/// }
+///
+/// fn create_user(name: &str, age: &str) -> Result<Result<(String, u8), Contextual<ParseIntError>>, DatabaseError> {
+///     let user = try_or_wrap!(validate_user(name, age)?, Ok, context = "creating user");
+///     Ok(Ok(user))
+/// }
+///
+/// let err = create_user("Ferris", "eight").unwrap().unwrap_err();
+/// assert_eq!(err.to_string(), "creating user: validating user Ferris: parsing age");
+/// assert_eq!(err.source().unwrap().to_string(), "invalid digit found in string");
+/// assert_eq!(
+///     err.messages().collect::<Vec<_>>(),
+///     ["creating user", "validating user Ferris", "parsing age"],
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contextual<E> {
+    /// From the innermost (first added) to the outermost
+    messages: Vec<Cow<'static, str>>,
+    source: E,
+}
+
+impl<E> Contextual<E> {
+    pub fn new(source: E, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            messages: alloc::vec![message.into()],
+            source,
+        }
+    }
+
+    /// Appends a message to the trail
+    pub fn context(mut self, message: impl Into<Cow<'static, str>>) -> Self {
+        self.messages.push(message.into());
+        self
+    }
+
+    /// Context messages, from the outermost (last added) to the innermost
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.messages.iter().rev().map(|message| &**message)
+    }
+
+    /// The error that the context was attached to
+    pub fn inner(&self) -> &E {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> E {
+        self.source
+    }
+}
+
+impl<E> From<Contextual<Contextual<E>>> for Contextual<E> {
+    fn from(outer: Contextual<Contextual<E>>) -> Self {
+        let mut inner = outer.source;
+        inner.messages.extend(outer.messages);
+        inner
+    }
+}
+
+/// Adds the message of a `context` or `with_context` argument, through autoref specialization:
+/// the macros call `(&ContextRef::new(err)).add_context(message)`, which resolves to
+/// [`ExtendContext`] if `err` is a `Contextual`, and to [`WrapContext`] (that requires one more
+/// autoref) otherwise.
+pub struct ContextRef<E>(Cell<Option<E>>);
+
+impl<E> ContextRef<E> {
+    pub fn new(err: E) -> Self {
+        Self(Cell::new(Some(err)))
+    }
+
+    fn take(&self) -> E {
+        self.0
+            .take()
+            .expect("the context of an error can only be added once")
+    }
+}
+
+pub trait ExtendContext {
+    type Source;
+    fn add_context<E2: FromError<Self::Source, M>, M>(
+        &self,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Contextual<E2>;
+}
+
+impl<E> ExtendContext for ContextRef<Contextual<E>> {
+    type Source = E;
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn add_context<E2: FromError<E, M>, M>(
+        &self,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Contextual<E2> {
+        let Contextual { messages, source } = self.take();
+        Contextual {
+            messages,
+            source: E2::from_error(source),
+        }
+        .context(message)
+    }
+}
+
+pub trait WrapContext {
+    type Source;
+    fn add_context<E2: FromError<Self::Source, M>, M>(
+        &self,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Contextual<E2>;
+}
+
+impl<E> WrapContext for &ContextRef<E> {
+    type Source = E;
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn add_context<E2: FromError<E, M>, M>(
+        &self,
+        message: impl Into<Cow<'static, str>>,
+    ) -> Contextual<E2> {
+        Contextual::new(E2::from_error(self.take()), message)
+    }
+}
+
+impl<E> fmt::Display for Contextual<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, message) in self.messages().enumerate() {
+            if i > 0 {
+                f.write_str(": ")?;
+            }
+            f.write_str(message)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E> core::error::Error for Contextual<E>
+where
+    E: core::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -0,0 +1,192 @@
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Adapters for futures that yield nested results (`Result<Result<T, E>, F>`)
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use try_or_wrap_s::TryOrWrapFutureExt;
+/// # use try_or_wrap_s::__test_support::block_on;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct InvalidInputError;
+/// #[derive(Debug, PartialEq)]
+/// struct DatabaseError;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum ApiError {
+///     InvalidInput(InvalidInputError),
+/// }
+/// impl From<InvalidInputError> for ApiError {
+///     fn from(err: InvalidInputError) -> Self {
+///         ApiError::InvalidInput(err)
+///     }
+/// }
+///
+/// async fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+///     match input {
+///         i32::MIN..=-1 => Ok(Err(InvalidInputError)),
+///         0 => Err(DatabaseError),
+///         _ => Ok(Ok(input as u32)),
+///     }
+/// }
+///
+/// fn foo(input: i32) -> impl std::future::Future<Output = Result<Result<u32, ApiError>, DatabaseError>> {
+///     validate_input_with_database(input).try_or_wrap()
+/// }
+///
+/// assert_eq!(block_on(foo(21)), Ok(Ok(21)));
+/// assert_eq!(block_on(foo(-1)), Ok(Err(ApiError::InvalidInput(InvalidInputError))));
+/// assert_eq!(block_on(foo(0)), Err(DatabaseError));
+/// # }
+/// ```
+pub trait TryOrWrapFutureExt<T, E, F>: Future<Output = Result<Result<T, E>, F>> + Sized {
+    /// Converts both error layers of the output with `Into`
+    ///
+    /// `fut.try_or_wrap().await` is equivalent to `Ok(Ok(try_or_wrap!(fut.await?, Ok)))`, but
+    /// can be used where an early return isn't possible (e.g. in future combinators).
+    ///
+    /// The conversion happens in `Future::poll`, which can't be `#[track_caller]`: unlike
+    /// `try_or_wrap!`, it can't wrap the error in a `Located` (with the `track-location` feature),
+    /// as the recorded location would be inside this crate instead of the caller's.
+    fn try_or_wrap<E2, F2>(self) -> TryOrWrapFuture<Self, E2, F2>
+    where
+        E: Into<E2>,
+        F: Into<F2>,
+    {
+        TryOrWrapFuture {
+            future: self,
+            _errors: PhantomData,
+        }
+    }
+}
+
+impl<Fut, T, E, F> TryOrWrapFutureExt<T, E, F> for Fut where
+    Fut: Future<Output = Result<Result<T, E>, F>>
+{
+}
+
+/// Future returned by [`TryOrWrapFutureExt::try_or_wrap`]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct TryOrWrapFuture<Fut, E2, F2> {
+    future: Fut,
+    _errors: PhantomData<fn() -> (E2, F2)>,
+}
+
+impl<Fut, T, E, F, E2, F2> Future for TryOrWrapFuture<Fut, E2, F2>
+where
+    Fut: Future<Output = Result<Result<T, E>, F>>,
+    E: Into<E2>,
+    F: Into<F2>,
+{
+    type Output = Result<Result<T, E2>, F2>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved out of `self`, and `TryOrWrapFuture` has no `Drop` impl
+        let future = unsafe { self.map_unchecked_mut(|this| &mut this.future) };
+        future.poll(cx).map(|output| match output {
+            Ok(Ok(val)) => Ok(Ok(val)),
+            Ok(Err(err)) => Ok(Err(err.into())),
+            Err(fault) => Err(fault.into()),
+        })
+    }
+}
+
+/// Stops a stream of nested results after its first outer error
+///
+/// Items are yielded unchanged, but after an outer error (`Err(F)`) is yielded the stream
+/// terminates, the same way `try_or_wrap!(expr?)` would have returned on it in a loop. Inner
+/// errors (`Ok(Err(E))`) don't terminate the stream.
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "std")] {
+/// use std::{pin::Pin, task::{Context, Poll}};
+/// use futures_core::Stream;
+/// use try_or_wrap_s::try_or_wrap_stream;
+/// # use try_or_wrap_s::__test_support::block_on;
+///
+/// struct Iter<I>(I);
+/// impl<I: Iterator + Unpin> Stream for Iter<I> {
+///     type Item = I::Item;
+///     fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+///         Poll::Ready(self.0.next())
+///     }
+/// }
+///
+/// let items: Vec<Result<Result<u32, &str>, &str>> =
+///     vec![Ok(Ok(1)), Ok(Err("invalid")), Ok(Ok(2)), Err("database"), Ok(Ok(3))];
+/// let mut stream = try_or_wrap_stream(Iter(items.into_iter()));
+/// let collected = block_on(std::future::poll_fn(|cx| {
+///     let mut collected = Vec::new();
+///     while let Poll::Ready(Some(item)) = Pin::new(&mut stream).poll_next(cx) {
+///         collected.push(item);
+///     }
+///     Poll::Ready(collected)
+/// }));
+/// assert_eq!(collected, vec![Ok(Ok(1)), Ok(Err("invalid")), Ok(Ok(2)), Err("database")]);
+/// # }
+/// ```
+#[cfg(feature = "stream")]
+pub fn try_or_wrap_stream<S, T, E, F>(stream: S) -> TryOrWrapStream<S>
+where
+    S: futures_core::Stream<Item = Result<Result<T, E>, F>>,
+{
+    TryOrWrapStream {
+        stream,
+        terminated: false,
+    }
+}
+
+/// Stream returned by [`try_or_wrap_stream`]
+#[cfg(feature = "stream")]
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct TryOrWrapStream<S> {
+    stream: S,
+    terminated: bool,
+}
+
+#[cfg(feature = "stream")]
+impl<S, T, E, F> futures_core::Stream for TryOrWrapStream<S>
+where
+    S: futures_core::Stream<Item = Result<Result<T, E>, F>>,
+{
+    type Item = Result<Result<T, E>, F>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // SAFETY: `stream` is never moved out of `self`, and `TryOrWrapStream` has no `Drop` impl
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.terminated {
+            return Poll::Ready(None);
+        }
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        let item = futures_core::ready!(stream.poll_next(cx));
+        if !matches!(item, Some(Ok(_))) {
+            this.terminated = true;
+        }
+        Poll::Ready(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.terminated {
+            (0, Some(0))
+        } else {
+            (0, self.stream.size_hint().1)
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S, T, E, F> futures_core::FusedStream for TryOrWrapStream<S>
+where
+    S: futures_core::Stream<Item = Result<Result<T, E>, F>>,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
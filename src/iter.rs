@@ -0,0 +1,102 @@
+use core::iter::FromIterator;
+
+/// Adapters for iterators over nested results (`Result<Result<T, E>, F>`)
+pub trait TryOrWrapIteratorExt<T, E, F>: Iterator<Item = Result<Result<T, E>, F>> + Sized {
+    /// Partitions the successes and the inner errors, stopping on the first outer error
+    ///
+    /// This behaves like calling `try_or_wrap!(item?)` on each item, except that inner errors are
+    /// collected instead of returned.
+    ///
+    /// # Example
+    /// ```
+    /// use try_or_wrap_s::TryOrWrapIteratorExt;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct InvalidInputError(i32);
+    /// #[derive(Debug, PartialEq)]
+    /// struct DatabaseError;
+    ///
+    /// fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+    ///     match input {
+    ///         i32::MIN..=-1 => Ok(Err(InvalidInputError(input))),
+    ///         0 => Err(DatabaseError),
+    ///         _ => Ok(Ok(input as u32)),
+    ///     }
+    /// }
+    ///
+    /// let (valid, invalid) = [1, -2, 3, -4]
+    ///     .iter()
+    ///     .map(|&input| validate_input_with_database(input))
+    ///     .try_or_wrap_collect::<Vec<_>, Vec<_>>()
+    ///     .unwrap();
+    /// assert_eq!(valid, vec![1, 3]);
+    /// assert_eq!(invalid, vec![InvalidInputError(-2), InvalidInputError(-4)]);
+    ///
+    /// let mut validated = Vec::new();
+    /// let result = [1, 0, 3]
+    ///     .iter()
+    ///     .inspect(|&&input| validated.push(input))
+    ///     .map(|&input| validate_input_with_database(input))
+    ///     .try_or_wrap_collect::<Vec<_>, Vec<_>>();
+    /// assert_eq!(result, Err(DatabaseError));
+    /// assert_eq!(validated, vec![1, 0]);
+    /// ```
+    fn try_or_wrap_collect<A, B>(self) -> Result<(A, B), F>
+    where
+        A: Default + Extend<T>,
+        B: Default + Extend<E>,
+    {
+        let mut oks = A::default();
+        let mut errs = B::default();
+        for item in self {
+            match item? {
+                Ok(val) => oks.extend(Some(val)),
+                Err(err) => errs.extend(Some(err)),
+            }
+        }
+        Ok((oks, errs))
+    }
+
+    /// Collects the successes, stopping on the first inner or outer error
+    ///
+    /// This behaves like calling `try_or_wrap!(item?)` on each item in a `for` loop: the first
+    /// outer error is returned as `Err(_)`, and the first inner error as `Ok(Err(_))`.
+    ///
+    /// # Example
+    /// ```
+    /// use try_or_wrap_s::TryOrWrapIteratorExt;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct InvalidInputError(i32);
+    /// #[derive(Debug, PartialEq)]
+    /// struct DatabaseError;
+    ///
+    /// fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+    ///     match input {
+    ///         i32::MIN..=-1 => Ok(Err(InvalidInputError(input))),
+    ///         0 => Err(DatabaseError),
+    ///         _ => Ok(Ok(input as u32)),
+    ///     }
+    /// }
+    ///
+    /// fn validate_all(inputs: &[i32]) -> Result<Result<Vec<u32>, InvalidInputError>, DatabaseError> {
+    ///     inputs.iter().map(|&input| validate_input_with_database(input)).collect_nested()
+    /// }
+    ///
+    /// assert_eq!(validate_all(&[1, 2, 3]), Ok(Ok(vec![1, 2, 3])));
+    /// assert_eq!(validate_all(&[1, -2, 0, -4]), Ok(Err(InvalidInputError(-2))));
+    /// assert_eq!(validate_all(&[1, 0, -3]), Err(DatabaseError));
+    /// ```
+    fn collect_nested<C>(self) -> Result<Result<C, E>, F>
+    where
+        C: FromIterator<T>,
+    {
+        // The `FromIterator` impls of `Result` stop on the first error of either layer
+        self.collect()
+    }
+}
+
+impl<I, T, E, F> TryOrWrapIteratorExt<T, E, F> for I where
+    I: Iterator<Item = Result<Result<T, E>, F>>
+{
+}
@@ -14,9 +14,253 @@
 //!
 //! fn validate_input_with_database(input: Input) -> Result<Result<ValidatedInput, InvalidInputError>, DatabaseError>;
 //! ```
+//!
+//! With the `#[try_or_wrap_fn]` function attribute, this can also be written `expr??`:
+//!
+//! ```
+//! use try_or_wrap_s::try_or_wrap_fn;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct InvalidInputError;
+//! #[derive(Debug, PartialEq)]
+//! struct DatabaseError;
+//!
+//! fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+//!     match input {
+//!         i32::MIN..=-1 => Ok(Err(InvalidInputError)),
+//!         0 => Err(DatabaseError),
+//!         _ => Ok(Ok(input as u32)),
+//!     }
+//! }
+//!
+//! #[try_or_wrap_fn]
+//! fn foo(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+//!     let validated_input = validate_input_with_database(input)??;
+//!     Ok(Ok(validated_input * 2))
+//! }
+//!
+//! assert_eq!(foo(21), Ok(Ok(42)));
+//! assert_eq!(foo(-1), Ok(Err(InvalidInputError)));
+//! assert_eq!(foo(0), Err(DatabaseError));
+//! ```
+//!
+//! If nested `Result`s are too awkward, [`Outcome`] provides a three-state equivalent that
+//! `try_or_wrap!` can also return into.
+//!
+//! # Cargo features
+//!
+//! - `std` (enabled by default): items that require the standard library. Without it, this crate
+//!   is `no_std`.
+//! - `alloc` (enabled by `std`): items that require an allocator, such as `WrapAccumulator`
+//!   or `Contextual`.
+//! - `stream`: `try_or_wrap_stream`, for `futures_core::Stream`s of nested results.
+//! - `track-location`: `Located`, to record which `try_or_wrap!` returned an error.
+//! - `backtrace` (requires `std`): `Traced`, to capture the backtrace of the `try_or_wrap!`
+//!   that returned an error.
+//! - `log` and `tracing`: emit an event with the `log` or `tracing` crate whenever
+//!   `try_or_wrap!` or `try_or_wrap_opt!` return early (see `LogLevel`).
+//! - `axum` and `actix-web` (require `std`): `NestedResponse`, rendering nested results as HTTP
+//!   responses.
+//! - `either`: `try_or_wrap_either!`, for functions returning `Result<Either<T, E>, F>`.
+//! - `serde` (requires `alloc`): `NestedResultSerde`, to serialize nested results as a tagged
+//!   three-way outcome.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod accumulator;
+mod branch;
+#[cfg(feature = "alloc")]
+mod contextual;
+mod future;
+mod iter;
+#[cfg(feature = "track-location")]
+mod located;
+#[cfg(any(feature = "log", feature = "tracing"))]
+mod logging;
+mod nested_result;
+mod outcome;
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+mod response;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "backtrace")]
+mod traced;
+mod transaction;
+
+#[cfg(feature = "alloc")]
+pub use accumulator::{RecordedValues, WrapAccumulator};
+pub use branch::{FromBreak, FromError, TryOrWrap};
+#[cfg(feature = "alloc")]
+pub use contextual::Contextual;
+#[cfg(feature = "stream")]
+pub use future::{try_or_wrap_stream, TryOrWrapStream};
+pub use future::{TryOrWrapFuture, TryOrWrapFutureExt};
+pub use iter::TryOrWrapIteratorExt;
+#[cfg(feature = "track-location")]
+pub use located::Located;
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub use logging::{log_level, set_log_level, LogLevel};
+pub use nested_result::NestedResultExt;
+pub use outcome::Outcome;
+#[cfg(any(feature = "axum", feature = "actix-web"))]
+pub use response::{ClientError, NestedResponse};
+#[cfg(feature = "serde")]
+pub use serialization::NestedResultSerde;
+#[cfg(feature = "backtrace")]
+pub use traced::Traced;
+pub use transaction::{transaction_nested, NestedTransaction};
+pub use try_or_wrap_s_derive::try_or_wrap_fn;
+
+#[doc(hidden)]
+/// Not public API: used by the macros
+pub mod __private {
+    use core::convert::Infallible;
+
+    pub use crate::branch::MappedBreak;
+
+    /// Residual that `map_err`, `context` and `with_context` can be applied to
+    pub trait MapErr {
+        type Error;
+        fn into_error(self) -> Self::Error;
+    }
+
+    impl<E> MapErr for Result<Infallible, E> {
+        type Error = E;
+        fn into_error(self) -> E {
+            match self {
+                Ok(never) => match never {},
+                Err(err) => err,
+            }
+        }
+    }
+
+    impl<E> MapErr for MappedBreak<E> {
+        type Error = E;
+        fn into_error(self) -> E {
+            self.0
+        }
+    }
+
+    #[cfg_attr(feature = "track-location", track_caller)]
+    pub fn map_err<R: MapErr, E2>(
+        residual: R,
+        map_err: impl FnOnce(R::Error) -> E2,
+    ) -> MappedBreak<E2> {
+        MappedBreak(map_err(residual.into_error()))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub use crate::contextual::{ContextRef, ExtendContext, WrapContext};
+
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    pub use crate::logging::{log_break, BreakEvent, BreakRef, DebugResidual, NoDebugResidual};
+
+    #[cfg(feature = "either")]
+    pub use either::Either;
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+/// Not public API: used by the doctests
+pub mod __test_support {
+    use std::future::Future;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker {
+        thread: Thread,
+        woken: AtomicBool,
+    }
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.woken.store(true, Ordering::Release);
+            self.thread.unpark();
+        }
+    }
+
+    /// Runs `future` to completion, parking the current thread until it is woken
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let thread_waker = Arc::new(ThreadWaker {
+            thread: thread::current(),
+            woken: AtomicBool::new(false),
+        });
+        let waker = Waker::from(thread_waker.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            // `park` can return spuriously, hence the flag
+            while !thread_waker.woken.swap(false, Ordering::Acquire) {
+                thread::park();
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+#[doc(hidden)]
+#[macro_export]
+/// Not public API: emits the event of an early return
+macro_rules! __log_break {
+    ($residual:expr, $level:expr, $wrapper:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{DebugResidual as _, NoDebugResidual as _};
+        $crate::__private::log_break(&$crate::__private::BreakEvent {
+            error: (&$crate::__private::BreakRef(&$residual)).debug_residual(),
+            level: $level,
+            wrapper: $wrapper,
+            module_path: ::core::module_path!(),
+            file: ::core::file!(),
+            line: ::core::line!(),
+            column: ::core::column!(),
+        })
+    }};
+}
+
+#[cfg(not(any(feature = "log", feature = "tracing")))]
+#[doc(hidden)]
+#[macro_export]
+/// Not public API: emits the event of an early return
+macro_rules! __log_break {
+    ($($args:tt)*) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+/// Not public API: `LogLevel` of a `log = <level>` argument
+macro_rules! __log_level {
+    (error) => {
+        $crate::LogLevel::Error
+    };
+    (warn) => {
+        $crate::LogLevel::Warn
+    };
+    (info) => {
+        $crate::LogLevel::Info
+    };
+    (debug) => {
+        $crate::LogLevel::Debug
+    };
+    (trace) => {
+        $crate::LogLevel::Trace
+    };
+}
 
 #[macro_export]
-/// Helper macro to wrap `?` into something else, for `Result`
+/// Helper macro to wrap `?` into something else, for `Result` (or any other [`TryOrWrap`] type)
 ///
 /// # Example
 /// ```ignore
@@ -29,30 +273,769 @@
 /// ````
 ///
 /// Note that the `Ok` parameter as shown in this example is optional, as it defaults to `Ok` if unspecified.
+///
+/// Besides `Result`, this works on `Option`, `ControlFlow`, `Poll<Result>`, `Poll<Option<Result>>`,
+/// and any type that implements [`TryOrWrap`]. The value given to the wrapper is built
+/// through [`FromBreak`] (e.g. `Err(err.into())` for a `Result`, `None` for an `Option`).
+///
+/// Instead of relying on `Into`, the error can be converted by a closure or function path. Its
+/// result is given to the wrapper as is, without another `From` conversion, so that the target
+/// type is known to the closure (e.g. for `map_err = Into::into`):
+/// ```
+/// # use try_or_wrap_s::try_or_wrap;
+/// use std::{convert::TryFrom, num::TryFromIntError};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum MyErr {
+///     Validation(String),
+///     TooLarge,
+/// }
+///
+/// impl From<TryFromIntError> for MyErr {
+///     fn from(_: TryFromIntError) -> Self {
+///         MyErr::TooLarge
+///     }
+/// }
+///
+/// fn validate(input: &str) -> Result<u32, String> {
+///     input.parse().map_err(|_| format!("{:?} is not a number", input))
+/// }
+///
+/// fn foo(input: &str) -> Result<Result<u8, MyErr>, std::io::Error> {
+///     let validated = try_or_wrap!(validate(input), Ok, map_err = MyErr::Validation);
+///     let doubled = try_or_wrap!(
+///         validated.checked_mul(2).ok_or(()),
+///         map_err = |()| MyErr::Validation("overflow".to_owned())
+///     );
+///     let small = try_or_wrap!(u8::try_from(doubled), Ok, map_err = Into::into);
+///     Ok(Ok(small))
+/// }
+///
+/// assert_eq!(foo("21").unwrap(), Ok(42));
+/// assert_eq!(foo("a").unwrap(), Err(MyErr::Validation("\"a\" is not a number".to_owned())));
+/// assert_eq!(foo("4000000000").unwrap(), Err(MyErr::Validation("overflow".to_owned())));
+/// assert_eq!(foo("200").unwrap(), Err(MyErr::TooLarge));
+/// ```
+///
+/// Similarly, `context = "message"` or `with_context = || format!(...)` wrap the error in a
+/// `Contextual` that keeps a trail of messages (requires the `alloc` feature). The error is
+/// converted with `From` before being wrapped:
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use std::num::ParseIntError;
+/// use try_or_wrap_s::{try_or_wrap, Contextual};
+///
+/// #[derive(Debug)]
+/// enum ConfigError {
+///     InvalidNumber(ParseIntError),
+/// }
+///
+/// impl From<ParseIntError> for ConfigError {
+///     fn from(err: ParseIntError) -> Self {
+///         ConfigError::InvalidNumber(err)
+///     }
+/// }
+///
+/// fn port(value: &str) -> Result<Result<u16, Contextual<ConfigError>>, std::io::Error> {
+///     Ok(Ok(try_or_wrap!(value.parse::<u16>(), Ok, context = "parsing port")))
+/// }
+///
+/// assert_eq!(port("80").unwrap().unwrap(), 80);
+/// let err = port("eighty").unwrap().unwrap_err();
+/// assert_eq!(err.to_string(), "parsing port");
+/// assert!(matches!(err.inner(), ConfigError::InvalidNumber(_)));
+/// # }
+/// ```
+///
+/// With the `log` or `tracing` feature, an event is emitted before returning, at the level set by
+/// `set_log_level` unless overridden with `log = <level>` (e.g. `try_or_wrap!(expr, Ok, log = warn)`).
+/// The error is only part of it when its type is known to implement `Debug` at the call site.
+///
+/// When there are more than two `Result` layers, `depth = n` applies the wrapper `n` times (up
+/// to 8): `try_or_wrap!(expr, depth = 2)` returns `Ok(Ok(Err(err.into())))`.
+/// ```
+/// # use try_or_wrap_s::try_or_wrap;
+/// #[derive(Debug, PartialEq)]
+/// struct BusinessErr;
+/// #[derive(Debug, PartialEq)]
+/// struct AuthErr;
+/// #[derive(Debug, PartialEq)]
+/// struct IoErr;
+///
+/// fn authenticate(user: &str) -> Result<Result<u32, AuthErr>, IoErr> {
+///     match user {
+///         "" => Err(IoErr),
+///         "root" => Ok(Ok(0)),
+///         _ => Ok(Err(AuthErr)),
+///     }
+/// }
+///
+/// fn check_quota(user_id: u32, amount: u32) -> Result<u32, BusinessErr> {
+///     if user_id == 0 && amount <= 10 { Ok(10 - amount) } else { Err(BusinessErr) }
+/// }
+///
+/// fn service(user: &str, amount: u32) -> Result<Result<Result<u32, BusinessErr>, AuthErr>, IoErr> {
+///     let user_id = try_or_wrap!(authenticate(user)?, Ok);
+///     let remaining = try_or_wrap!(check_quota(user_id, amount), depth = 2);
+///     Ok(Ok(Ok(remaining)))
+/// }
+///
+/// assert_eq!(service("root", 3), Ok(Ok(Ok(7))));
+/// assert_eq!(service("root", 11), Ok(Ok(Err(BusinessErr))));
+/// assert_eq!(service("guest", 3), Ok(Err(AuthErr)));
+/// assert_eq!(service("", 3), Err(IoErr));
+///
+/// fn depth_1(input: Result<u32, BusinessErr>) -> Result<Result<u32, BusinessErr>, ()> {
+///     Ok(Ok(try_or_wrap!(input, depth = 1)))
+/// }
+/// fn depth_2(input: Result<u32, BusinessErr>) -> Result<Result<Result<u32, BusinessErr>, ()>, ()> {
+///     Ok(Ok(Ok(try_or_wrap!(input, depth = 2))))
+/// }
+/// fn depth_3(
+///     input: Result<u32, BusinessErr>,
+/// ) -> Result<Result<Result<Result<u32, BusinessErr>, ()>, ()>, ()> {
+///     Ok(Ok(Ok(Ok(try_or_wrap!(input, Ok, depth = 3)))))
+/// }
+/// fn depth_4(
+///     input: Result<u32, BusinessErr>,
+/// ) -> Result<Result<Result<Result<Result<u32, BusinessErr>, ()>, ()>, ()>, ()> {
+///     Ok(Ok(Ok(Ok(Ok(try_or_wrap!(input, map_err = |e| e, depth = 4, log = info))))))
+/// }
+///
+/// assert_eq!(depth_1(Ok(1)), Ok(Ok(1)));
+/// assert_eq!(depth_1(Err(BusinessErr)), Ok(Err(BusinessErr)));
+/// assert_eq!(depth_2(Ok(2)), Ok(Ok(Ok(2))));
+/// assert_eq!(depth_2(Err(BusinessErr)), Ok(Ok(Err(BusinessErr))));
+/// assert_eq!(depth_3(Ok(3)), Ok(Ok(Ok(Ok(3)))));
+/// assert_eq!(depth_3(Err(BusinessErr)), Ok(Ok(Ok(Err(BusinessErr)))));
+/// assert_eq!(depth_4(Ok(4)), Ok(Ok(Ok(Ok(Ok(4))))));
+/// assert_eq!(depth_4(Err(BusinessErr)), Ok(Ok(Ok(Ok(Err(BusinessErr))))));
+/// ```
+///
+/// Prefixing the arguments with a label (`try_or_wrap!('label: expr, Ok)`) makes it
+/// `break 'label` with the wrapped error instead of returning, which is useful to short-circuit
+/// only a `loop` or a labeled block:
+/// ```
+/// # use try_or_wrap_s::try_or_wrap;
+/// fn parse_all(items: &[&str]) -> Result<Vec<Result<u32, std::num::ParseIntError>>, std::io::Error> {
+///     let mut results = Vec::new();
+///     for item in items {
+///         let result: Result<Result<u32, _>, std::io::Error> = 'item: {
+///             let parsed: u32 = try_or_wrap!('item: item.parse(), Ok);
+///             Ok(Ok(parsed * 2))
+///         };
+///         results.push(result?);
+///     }
+///     Ok(results)
+/// }
+///
+/// let results = parse_all(&["1", "a", "3"]).unwrap();
+/// assert_eq!(results[0], Ok(2));
+/// assert!(results[1].is_err());
+/// assert_eq!(results[2], Ok(6));
+/// ```
 macro_rules! try_or_wrap {
-    ($expr:expr) => {
-        try_or_wrap! { $expr, Ok }
-    };
-    ($expr:expr, $wrapper:expr) => {
-        match $expr {
-            std::result::Result::Ok(val) => val,
-            std::result::Result::Err(err) => {
-                return $wrapper(std::result::Result::Err(std::convert::Into::into(err)))
+    (@impl [$($exit:tt)+] $expr:expr, [$($wrapper:expr),+] [$($option:tt)*]) => {
+        match $crate::TryOrWrap::branch($expr) {
+            ::core::ops::ControlFlow::Continue(val) => val,
+            ::core::ops::ControlFlow::Break(residual) => {
+                $crate::__log_break!(
+                    residual,
+                    $crate::try_or_wrap!(@log_level [$($option)*]),
+                    ::core::stringify!($($wrapper).+)
+                );
+                $($exit)+ $crate::try_or_wrap!(@wrap [$($wrapper),+] $crate::FromBreak::from_break(
+                    $crate::try_or_wrap!(@map_err [$($option)*] residual)
+                ))
             }
         }
     };
+    (@wrap [$wrapper:expr] $value:expr) => {
+        $wrapper($value)
+    };
+    (@wrap [$wrapper:expr, $($rest:expr),+] $value:expr) => {
+        $wrapper($crate::try_or_wrap!(@wrap [$($rest),+] $value))
+    };
+    (@map_err [] $residual:expr) => {
+        $residual
+    };
+    (@map_err [map_err = $map_err:expr $(, $($option:tt)*)?] $residual:expr) => {
+        $crate::try_or_wrap!(
+            @map_err [$($($option)*)?] $crate::__private::map_err($residual, $map_err)
+        )
+    };
+    (@map_err [context = $context:expr $(, $($option:tt)*)?] $residual:expr) => {
+        $crate::try_or_wrap!(@add_context [$($($option)*)?] $residual, $context)
+    };
+    (@map_err [with_context = $context:expr $(, $($option:tt)*)?] $residual:expr) => {
+        $crate::try_or_wrap!(@add_context [$($($option)*)?] $residual, ($context)())
+    };
+    (@map_err [log = $level:ident $(, $($option:tt)*)?] $residual:expr) => {
+        $crate::try_or_wrap!(@map_err [$($($option)*)?] $residual)
+    };
+    (@map_err [$($option:tt)*] $residual:expr) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap!` option: `",
+            ::core::stringify!($($option)*),
+            "` (expected `map_err`, `context`, `with_context` or `log`)"
+        ))
+    };
+    (@add_context [$($option:tt)*] $residual:expr, $message:expr) => {
+        $crate::try_or_wrap!(
+            @map_err [$($option)*]
+            $crate::__private::map_err($residual, |err| {
+                #[allow(unused_imports)]
+                use $crate::__private::{ExtendContext as _, WrapContext as _};
+                (&$crate::__private::ContextRef::new(err)).add_context($message)
+            })
+        )
+    };
+    (@log_level []) => {
+        ::core::option::Option::None
+    };
+    (@log_level [log = $level:ident $(, $($option:tt)*)?]) => {
+        ::core::option::Option::Some($crate::__log_level!($level))
+    };
+    (@log_level [$name:ident = $value:expr $(, $($option:tt)*)?]) => {
+        $crate::try_or_wrap!(@log_level [$($($option)*)?])
+    };
+    // Invalid options are reported by `@map_err`
+    (@log_level [$($option:tt)*]) => {
+        ::core::option::Option::None
+    };
+    // `@depth $exit $expr, [outer wrappers] $wrapper, [options before depth] [remaining options]`
+    (@depth $exit:tt $expr:expr, $outer:tt $wrapper:expr, [$($before:tt)*] []) => {
+        $crate::try_or_wrap! { @repeat $exit $expr, $outer $wrapper, [$($before)*] 1 }
+    };
+    (@depth $exit:tt $expr:expr, $outer:tt $wrapper:expr, [$($before:tt)*] [depth = $depth:tt $(, $($option:tt)*)?]) => {
+        $crate::try_or_wrap! { @repeat $exit $expr, $outer $wrapper, [$($before)* $($($option)*)?] $depth }
+    };
+    (@depth $exit:tt $expr:expr, $outer:tt $wrapper:expr, [$($before:tt)*] [log = $level:ident $(, $($option:tt)*)?]) => {
+        $crate::try_or_wrap! { @depth $exit $expr, $outer $wrapper, [$($before)* log = $level,] [$($($option)*)?] }
+    };
+    (@depth $exit:tt $expr:expr, $outer:tt $wrapper:expr, [$($before:tt)*] [$name:ident = $value:expr $(, $($option:tt)*)?]) => {
+        $crate::try_or_wrap! { @depth $exit $expr, $outer $wrapper, [$($before)* $name = $value,] [$($($option)*)?] }
+    };
+    // Invalid options are reported by `@map_err`
+    (@depth $exit:tt $expr:expr, $outer:tt $wrapper:expr, [$($before:tt)*] [$($option:tt)*]) => {
+        $crate::try_or_wrap! { @repeat $exit $expr, $outer $wrapper, [$($before)* $($option)*] 1 }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 1) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 2) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 3) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 4) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 5) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 6) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w, $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 7) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w, $w, $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, [$($outer:expr),*] $w:expr, $options:tt 8) => {
+        $crate::try_or_wrap! { @impl $exit $expr, [$($outer,)* $w, $w, $w, $w, $w, $w, $w, $w] $options }
+    };
+    (@repeat $exit:tt $expr:expr, $outer:tt $w:expr, $options:tt $depth:tt) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap!` depth: `",
+            ::core::stringify!($depth),
+            "` (expected an integer literal between 1 and 8)"
+        ))
+    };
+    (@parse [$($exit:tt)+] $expr:expr $(, $name:ident = $($option:tt)*)?) => {
+        $crate::try_or_wrap! { @depth [$($exit)+] $expr, [] Ok, [] [$($name = $($option)*)?] }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr $(, $($option:tt)*)?) => {
+        $crate::try_or_wrap! { @depth [$($exit)+] $expr, [] $wrapper, [] [$($($option)*)?] }
+    };
+    (@parse [$($exit:tt)+] $($args:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap!` arguments: `",
+            ::core::stringify!($($args)*),
+            "` (expected `expr`, `expr, wrapper` or `expr, wrapper, option = value, ...`)"
+        ))
+    };
+    ($label:lifetime: $($args:tt)+) => {
+        $crate::try_or_wrap! { @parse [break $label] $($args)+ }
+    };
+    ($($args:tt)+) => {
+        $crate::try_or_wrap! { @parse [return] $($args)+ }
+    };
 }
 
 /// Same as `try_or_wrap`, but for `Option`
+///
+/// By default, `None` is returned as `$wrapper(None)`. With `else = err`, it is instead returned
+/// as `$wrapper(Err(err.into()))`, `err` being only evaluated if the value is `None`:
+/// ```
+/// # use try_or_wrap_s::try_or_wrap_opt;
+/// #[derive(Debug, PartialEq)]
+/// struct NotFound(u32);
+///
+/// fn lookup(id: u32) -> Option<&'static str> {
+///     ["zero", "one"].get(id as usize).copied()
+/// }
+///
+/// fn name_length(id: u32) -> Result<Result<usize, NotFound>, std::io::Error> {
+///     let name = try_or_wrap_opt!(lookup(id), Ok, else = NotFound(id));
+///     Ok(Ok(name.len()))
+/// }
+///
+/// assert_eq!(name_length(1).unwrap(), Ok(3));
+/// assert_eq!(name_length(2).unwrap(), Err(NotFound(2)));
+/// ```
+///
+/// As with `try_or_wrap`, a label can be given to `break` instead of returning
+/// (`try_or_wrap_opt!('label: expr, Ok)`), and `log = <level>` can be given last to override the
+/// level of the event emitted with the `log` or `tracing` feature.
 #[macro_export]
 macro_rules! try_or_wrap_opt {
-    ($expr:expr) => {
-        try_or_wrap_opt! { $expr, Ok }
+    (@parse [$($exit:tt)+] $expr:expr $(, log = $level:ident)?) => {
+        $crate::try_or_wrap_opt! { @parse [$($exit)+] $expr, Ok $(, log = $level)? }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, else = $err:expr $(, log = $level:ident)?) => {
+        $crate::try_or_wrap_opt! { @parse [$($exit)+] $expr, Ok, else = $err $(, log = $level)? }
     };
-    ($expr:expr, $wrapper:expr) => {
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr, else = $err:expr $(, log = $level:ident)?) => {
         match $expr {
-            std::option::Option::Some(val) => val,
-            std::option::Option::None => return $wrapper(std::option::Option::None),
+            ::core::option::Option::Some(val) => val,
+            ::core::option::Option::None => {
+                let residual = ::core::result::Result::<::core::convert::Infallible, _>::Err($err);
+                $crate::__log_break!(
+                    residual,
+                    $crate::try_or_wrap_opt!(@log_level $($level)?),
+                    ::core::stringify!($wrapper)
+                );
+                $($exit)+ $wrapper($crate::FromBreak::from_break(residual))
+            }
         }
     };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr $(, log = $level:ident)?) => {
+        match $expr {
+            ::core::option::Option::Some(val) => val,
+            ::core::option::Option::None => {
+                $crate::__log_break!(
+                    ::core::option::Option::<::core::convert::Infallible>::None,
+                    $crate::try_or_wrap_opt!(@log_level $($level)?),
+                    ::core::stringify!($wrapper)
+                );
+                $($exit)+ $wrapper(::core::option::Option::None)
+            }
+        }
+    };
+    (@log_level) => {
+        ::core::option::Option::None
+    };
+    (@log_level $level:ident) => {
+        ::core::option::Option::Some($crate::__log_level!($level))
+    };
+    (@parse [$($exit:tt)+] $($args:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap_opt!` arguments: `",
+            ::core::stringify!($($args)*),
+            "` (expected `expr`, `expr, wrapper`, `expr, wrapper, else = err`, optionally followed by `log = level`)"
+        ))
+    };
+    ($label:lifetime: $($args:tt)+) => {
+        $crate::try_or_wrap_opt! { @parse [break $label] $($args)+ }
+    };
+    ($($args:tt)+) => {
+        $crate::try_or_wrap_opt! { @parse [return] $($args)+ }
+    };
+}
+
+/// Same as `try_or_wrap`, but returns the error as `ControlFlow::Break`
+///
+/// `try_or_break!(expr)` is `try_or_wrap!(expr, ControlFlow::Break)`: it returns
+/// `ControlFlow::Break(Err(err.into()))` for a `Result`, and `ControlFlow::Break(None)` for an
+/// `Option`. This is useful in closures given to `try_fold`, `try_for_each` or visitors.
+/// The arguments of `try_or_wrap` (label, `map_err = ...`, `context = ...`, ...) are supported.
+///
+/// # Example
+/// ```
+/// use std::{num::ParseIntError, ops::ControlFlow};
+/// use try_or_wrap_s::try_or_break;
+///
+/// fn sum(items: &[&str]) -> ControlFlow<Result<u32, ParseIntError>, u32> {
+///     items.iter().try_fold(0, |acc, item| {
+///         let n: u32 = try_or_break!(item.parse());
+///         if n == 0 {
+///             return ControlFlow::Break(Ok(acc));
+///         }
+///         ControlFlow::Continue(acc + n)
+///     })
+/// }
+///
+/// assert_eq!(sum(&["1", "2", "3"]), ControlFlow::Continue(6));
+/// assert_eq!(sum(&["1", "2", "0", "3"]), ControlFlow::Break(Ok(3)));
+/// assert!(matches!(sum(&["1", "a", "3"]), ControlFlow::Break(Err(_))));
+///
+/// fn checked_sum(items: &[u8]) -> ControlFlow<Option<&'static str>> {
+///     let mut total = 0u8;
+///     items.iter().try_for_each(|&item| {
+///         total = try_or_break!(total.checked_add(item));
+///         ControlFlow::Continue(())
+///     })?;
+///     if total % 2 == 1 {
+///         return ControlFlow::Break(Some("odd"));
+///     }
+///     ControlFlow::Continue(())
+/// }
+///
+/// assert_eq!(checked_sum(&[1, 2, 3]), ControlFlow::Continue(()));
+/// assert_eq!(checked_sum(&[1, 2]), ControlFlow::Break(Some("odd")));
+/// assert_eq!(checked_sum(&[200, 100]), ControlFlow::Break(None));
+/// ```
+#[macro_export]
+macro_rules! try_or_break {
+    ($label:lifetime: $expr:expr $(, $($option:tt)*)?) => {
+        $crate::try_or_wrap! {
+            @impl [break $label] $expr, [::core::ops::ControlFlow::Break] [$($($option)*)?]
+        }
+    };
+    ($expr:expr $(, $($option:tt)*)?) => {
+        $crate::try_or_wrap! {
+            @impl [return] $expr, [::core::ops::ControlFlow::Break] [$($($option)*)?]
+        }
+    };
+}
+
+/// Same as `try_or_wrap`, but for `Poll`, in hand-written `Future::poll` implementations
+///
+/// `Poll::Pending` is returned as is (like `ready!`), `Poll::Ready(val)` goes through
+/// `try_or_wrap`, and errors are returned as `Poll::Ready($wrapper(Err(err.into())))`.
+/// The arguments of `try_or_wrap` (label, `depth = n`, `map_err = ...`, ...) are supported.
+///
+/// # Example
+/// ```
+/// use std::{collections::VecDeque, future::Future, pin::Pin, task::{Context, Poll, Waker}};
+/// use try_or_wrap_s::try_or_wrap_poll;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct InvalidLength(u8);
+/// #[derive(Debug, PartialEq)]
+/// struct ConnectionReset;
+///
+/// /// Fake connection, that gives the results of `poll_byte` in order
+/// struct Connection(VecDeque<Poll<Result<u8, ConnectionReset>>>);
+///
+/// impl Connection {
+///     fn poll_byte(&mut self, _cx: &mut Context<'_>) -> Poll<Result<u8, ConnectionReset>> {
+///         self.0.pop_front().unwrap()
+///     }
+/// }
+///
+/// fn check_length(byte: u8) -> Result<u8, InvalidLength> {
+///     if byte == 0 { Err(InvalidLength(byte)) } else { Ok(byte) }
+/// }
+///
+/// /// Reads a length, that must be non-zero
+/// struct ReadLength(Connection);
+///
+/// impl Future for ReadLength {
+///     type Output = Result<Result<u8, InvalidLength>, ConnectionReset>;
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+///         let byte = self.0.poll_byte(cx)?;
+///         let length = try_or_wrap_poll!(byte.map(check_length), Ok);
+///         Poll::Ready(Ok(Ok(length)))
+///     }
+/// }
+///
+/// let mut cx = Context::from_waker(Waker::noop());
+/// let mut future = ReadLength(Connection(VecDeque::from([
+///     Poll::Pending,
+///     Poll::Ready(Ok(3)),
+///     Poll::Ready(Ok(0)),
+///     Poll::Ready(Err(ConnectionReset)),
+/// ])));
+/// assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Pending);
+/// assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(Ok(Ok(3))));
+/// assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(Ok(Err(InvalidLength(0)))));
+/// assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Ready(Err(ConnectionReset)));
+/// ```
+#[macro_export]
+macro_rules! try_or_wrap_poll {
+    (@impl [$($exit:tt)+] $expr:expr, $wrapper:expr, [$($option:tt)*]) => {
+        match $expr {
+            ::core::task::Poll::Pending => {
+                $($exit)+ ::core::task::Poll::Pending
+            }
+            ::core::task::Poll::Ready(val) => $crate::try_or_wrap! {
+                @depth [$($exit)+] val, [::core::task::Poll::Ready] $wrapper, [] [$($option)*]
+            },
+        }
+    };
+    (@parse [$($exit:tt)+] $expr:expr $(, $name:ident = $($option:tt)*)?) => {
+        $crate::try_or_wrap_poll! { @impl [$($exit)+] $expr, Ok, [$($name = $($option)*)?] }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr $(, $($option:tt)*)?) => {
+        $crate::try_or_wrap_poll! { @impl [$($exit)+] $expr, $wrapper, [$($($option)*)?] }
+    };
+    (@parse [$($exit:tt)+] $($args:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap_poll!` arguments: `",
+            ::core::stringify!($($args)*),
+            "` (expected `expr`, `expr, wrapper` or `expr, wrapper, option = value, ...`)"
+        ))
+    };
+    ($label:lifetime: $($args:tt)+) => {
+        $crate::try_or_wrap_poll! { @parse [break $label] $($args)+ }
+    };
+    ($($args:tt)+) => {
+        $crate::try_or_wrap_poll! { @parse [return] $($args)+ }
+    };
+}
+
+/// Scopes the early returns of `try_or_wrap!` and friends (as well as `?`) to a block
+///
+/// This emulates `try` blocks with an immediately-invoked closure: the block evaluates to the
+/// nested result that `try_or_wrap!` or `try_or_wrap_opt!` short-circuit to, or to its final
+/// expression. As in any closure, `.await` or `break`/`continue` to outer loops can't be used in
+/// it: a labeled block with `try_or_wrap!('label: ...)` can be used instead.
+///
+/// # Example
+/// ```
+/// use std::num::ParseIntError;
+/// use try_or_wrap_s::{try_or_wrap, try_or_wrap_block, try_or_wrap_opt};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum InvalidPair {
+///     Parse(ParseIntError),
+///     MissingSeparator,
+/// }
+/// impl From<ParseIntError> for InvalidPair {
+///     fn from(err: ParseIntError) -> Self {
+///         InvalidPair::Parse(err)
+///     }
+/// }
+/// #[derive(Debug, PartialEq)]
+/// struct StorageFull;
+///
+/// fn store_pairs(lines: &[&str], capacity: usize) -> Result<Vec<Result<u32, InvalidPair>>, StorageFull> {
+///     let mut stored = Vec::new();
+///     for line in lines {
+///         let result: Result<Result<u32, InvalidPair>, StorageFull> = try_or_wrap_block! {
+///             let (a, b) = try_or_wrap_opt!(line.split_once(','), Ok, else = InvalidPair::MissingSeparator);
+///             let sum = try_or_wrap!(a.parse::<u32>(), Ok) + try_or_wrap!(b.parse::<u32>(), Ok);
+///             if stored.len() == capacity {
+///                 return Err(StorageFull);
+///             }
+///             Ok(Ok(sum))
+///         };
+///         stored.push(result?);
+///     }
+///     Ok(stored)
+/// }
+///
+/// let stored = store_pairs(&["1,2", "3", "4,a", "5,6"], 10).unwrap();
+/// assert_eq!(stored[0], Ok(3));
+/// assert_eq!(stored[1], Err(InvalidPair::MissingSeparator));
+/// assert!(matches!(stored[2], Err(InvalidPair::Parse(_))));
+/// assert_eq!(stored[3], Ok(11));
+/// assert_eq!(store_pairs(&["1,2", "3,4"], 1), Err(StorageFull));
+/// ```
+#[macro_export]
+macro_rules! try_or_wrap_block {
+    ($($body:tt)*) => {
+        (|| {
+            $($body)*
+        })()
+    };
+}
+
+/// Declares an enum of inner errors, that each of the errors it contains can be converted into
+///
+/// Each variant holds a single error type, and gets a `From` impl so that `try_or_wrap!`
+/// converts any of these errors into the enum. `Display` and `Error` are implemented by
+/// forwarding to the variant's error, which must implement them. `Error` is `core::error::Error`,
+/// so this works the same with or without the `std` feature.
+///
+/// Attributes (including `#[derive]`s and doc comments) are kept on the enum and its variants.
+/// The enum must implement `Debug` for its `Error` impl.
+///
+/// # Example
+/// ```
+/// use std::fmt;
+/// use try_or_wrap_s::{inner_errors, try_or_wrap};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct NotFoundErr(u32);
+/// impl fmt::Display for NotFoundErr {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         write!(f, "item {} not found", self.0)
+///     }
+/// }
+/// impl std::error::Error for NotFoundErr {}
+///
+/// #[derive(Debug, PartialEq)]
+/// struct ForbiddenErr;
+/// impl fmt::Display for ForbiddenErr {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str("forbidden")
+///     }
+/// }
+/// impl std::error::Error for ForbiddenErr {}
+///
+/// inner_errors! {
+///     /// Errors that are reported to the API client
+///     #[derive(Debug, PartialEq)]
+///     pub enum ApiFailure {
+///         NotFound(NotFoundErr),
+///         Forbidden(ForbiddenErr),
+///     }
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// struct DbError;
+///
+/// fn find(id: u32) -> Result<Result<&'static str, NotFoundErr>, DbError> {
+///     Ok(if id == 1 { Ok("secret") } else { Err(NotFoundErr(id)) })
+/// }
+///
+/// fn check_access(user: &str) -> Result<(), ForbiddenErr> {
+///     if user == "admin" { Ok(()) } else { Err(ForbiddenErr) }
+/// }
+///
+/// fn read(user: &str, id: u32) -> Result<Result<&'static str, ApiFailure>, DbError> {
+///     try_or_wrap!(check_access(user), Ok);
+///     let item = try_or_wrap!(find(id)?, Ok);
+///     Ok(Ok(item))
+/// }
+///
+/// assert_eq!(read("admin", 1), Ok(Ok("secret")));
+/// assert_eq!(read("admin", 2), Ok(Err(ApiFailure::NotFound(NotFoundErr(2)))));
+/// assert_eq!(read("guest", 1), Ok(Err(ApiFailure::Forbidden(ForbiddenErr))));
+/// assert_eq!(read("admin", 2).unwrap().unwrap_err().to_string(), "item 2 not found");
+/// let _: &dyn std::error::Error = &ApiFailure::Forbidden(ForbiddenErr);
+/// ```
+#[macro_export]
+macro_rules! inner_errors {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident($error:ty)),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis enum $name {
+            $($(#[$variant_attr])* $variant($error),)+
+        }
+
+        $(
+            impl ::core::convert::From<$error> for $name {
+                fn from(err: $error) -> Self {
+                    $name::$variant(err)
+                }
+            }
+        )+
+
+        impl ::core::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    $($name::$variant(err) => ::core::fmt::Display::fmt(err, f),)+
+                }
+            }
+        }
+
+        impl ::core::error::Error for $name {
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                match self {
+                    $($name::$variant(err) => ::core::error::Error::source(err),)+
+                }
+            }
+        }
+    };
+}
+
+/// Same as `try_or_wrap`, but for [`Either`](either::Either) (requires the `either` feature)
+///
+/// By default, `Left(val)` is unwrapped to `val`, and `Right(err)` is returned as
+/// `$wrapper(Right(err.into()))`. With `Left` as last argument, the sides are swapped: `Right(val)`
+/// is unwrapped, and `Left(err)` is returned as `$wrapper(Left(err.into()))`.
+///
+/// # Example
+/// ```
+/// use either::Either::{self, Left, Right};
+/// use try_or_wrap_s::try_or_wrap_either;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct InvalidInputError;
+/// #[derive(Debug, PartialEq)]
+/// struct DatabaseError;
+///
+/// fn validate_input_with_database(input: i32) -> Result<Either<u32, InvalidInputError>, DatabaseError> {
+///     match input {
+///         i32::MIN..=-1 => Ok(Right(InvalidInputError)),
+///         0 => Err(DatabaseError),
+///         _ => Ok(Left(input as u32)),
+///     }
+/// }
+///
+/// fn foo(input: i32) -> Result<Either<u32, InvalidInputError>, DatabaseError> {
+///     let validated_input = try_or_wrap_either!(validate_input_with_database(input)?, Ok);
+///     Ok(Left(validated_input * 2))
+/// }
+///
+/// assert_eq!(foo(21), Ok(Left(42)));
+/// assert_eq!(foo(-1), Ok(Right(InvalidInputError)));
+/// assert_eq!(foo(0), Err(DatabaseError));
+///
+/// fn swapped(input: Either<&'static str, u32>) -> Result<Either<&'static str, u32>, DatabaseError> {
+///     let value = try_or_wrap_either!(input, Ok, Left);
+///     Ok(Right(value + 1))
+/// }
+///
+/// assert_eq!(swapped(Right(1)), Ok(Right(2)));
+/// assert_eq!(swapped(Left("invalid")), Ok(Left("invalid")));
+/// ```
+///
+/// As with `try_or_wrap`, a label can be given to `break` instead of returning
+/// (`try_or_wrap_either!('label: expr, Ok)`).
+#[cfg(feature = "either")]
+#[macro_export]
+macro_rules! try_or_wrap_either {
+    (@parse [$($exit:tt)+] $expr:expr) => {
+        $crate::try_or_wrap_either! { @parse [$($exit)+] $expr, Ok, Right }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr) => {
+        $crate::try_or_wrap_either! { @parse [$($exit)+] $expr, $wrapper, Right }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr, Right) => {
+        $crate::try_or_wrap_either! { @impl [$($exit)+] $expr, $wrapper, Left, Right }
+    };
+    (@parse [$($exit:tt)+] $expr:expr, $wrapper:expr, Left) => {
+        $crate::try_or_wrap_either! { @impl [$($exit)+] $expr, $wrapper, Right, Left }
+    };
+    (@parse [$($exit:tt)+] $($args:tt)*) => {
+        ::core::compile_error!(::core::concat!(
+            "invalid `try_or_wrap_either!` arguments: `",
+            ::core::stringify!($($args)*),
+            "` (expected `expr`, `expr, wrapper`, `expr, wrapper, Right` or `expr, wrapper, Left`)"
+        ))
+    };
+    (@impl [$($exit:tt)+] $expr:expr, $wrapper:expr, $val:ident, $err:ident) => {
+        match $expr {
+            $crate::__private::Either::$val(val) => val,
+            $crate::__private::Either::$err(err) => {
+                $crate::__log_break!(err, ::core::option::Option::None, ::core::stringify!($wrapper));
+                $($exit)+ $wrapper($crate::__private::Either::$err(::core::convert::From::from(err)))
+            }
+        }
+    };
+    ($label:lifetime: $($args:tt)+) => {
+        $crate::try_or_wrap_either! { @parse [break $label] $($args)+ }
+    };
+    ($($args:tt)+) => {
+        $crate::try_or_wrap_either! { @parse [return] $($args)+ }
+    };
 }
@@ -0,0 +1,116 @@
+use core::fmt;
+use core::panic::Location;
+
+use crate::branch::FromMappedError;
+use crate::FromError;
+
+/// Error along with the location of the `try_or_wrap!` invocation that returned it
+///
+/// Use `Located<E>` as the inner error type of the return type: `try_or_wrap!` converts the error
+/// into `E` with `From` (or with `map_err`), then records its own location.
+///
+/// # Example
+/// ```
+/// use std::num::ParseIntError;
+/// use try_or_wrap_s::{try_or_wrap, Located};
+///
+/// #[derive(Debug)]
+/// enum ConfigError {
+///     InvalidNumber(ParseIntError),
+///     Empty,
+/// }
+///
+/// impl From<ParseIntError> for ConfigError {
+///     fn from(err: ParseIntError) -> Self {
+///         ConfigError::InvalidNumber(err)
+///     }
+/// }
+///
+/// fn parse(input: &str) -> Result<Result<u32, Located<ConfigError>>, std::io::Error> {
+///     let parsed = try_or_wrap!(input.parse::<u32>(), Ok);
+///     Ok(Ok(parsed))
+/// }
+///
+/// fn parse_non_empty(input: &str) -> Result<Result<u32, Located<ConfigError>>, std::io::Error> {
+///     let input = try_or_wrap!(
+///         Some(input).filter(|input| !input.is_empty()).ok_or(()),
+///         Ok,
+///         map_err = |()| ConfigError::Empty
+///     );
+///     parse(input)
+/// }
+///
+/// let err = parse("a").unwrap().unwrap_err();
+/// assert!(matches!(err.error(), ConfigError::InvalidNumber(_)));
+/// assert_eq!(err.location().file(), file!());
+/// assert_eq!(err.location().line(), line!() - 16);
+///
+/// let err = parse_non_empty("").unwrap().unwrap_err();
+/// assert!(matches!(err.error(), ConfigError::Empty));
+/// assert_eq!(err.location().line(), line!() - 15);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Located<E> {
+    error: E,
+    location: &'static Location<'static>,
+}
+
+impl<E> Located<E> {
+    /// Wraps `error`, recording the location of the caller
+    #[track_caller]
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            location: Location::caller(),
+        }
+    }
+
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+/// Marker of the [`FromError`] implementation of `Located`
+#[doc(hidden)]
+pub enum Locate {}
+
+impl<E, E2> FromError<E, Locate> for Located<E2>
+where
+    E2: From<E>,
+{
+    #[track_caller]
+    fn from_error(err: E) -> Self {
+        Self::new(E2::from(err))
+    }
+}
+
+impl<E> FromMappedError<E, Locate> for Located<E> {
+    #[track_caller]
+    fn from_mapped_error(err: E) -> Self {
+        Self::new(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Located<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {})", self.error, self.location)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E> std::error::Error for Located<E>
+where
+    E: std::error::Error,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
@@ -0,0 +1,264 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Level of the events emitted when `try_or_wrap!` or `try_or_wrap_opt!` return early
+///
+/// The level of all the events is set with [`set_log_level`] (`Debug` by default), and can be
+/// overridden for a single invocation with the `log = <level>` argument:
+/// `try_or_wrap!(expr, Ok, log = warn)`.
+///
+/// Events are emitted with the `log` crate if the `log` feature is enabled, and with the
+/// `tracing` crate if the `tracing` feature is enabled. They contain the `Debug` of the error
+/// (when it implements `Debug`), the call site and the wrapper.
+///
+/// When both features are enabled, events are only emitted with `tracing`, and the `log` feature
+/// enables `tracing`'s own `log` feature: `tracing` then forwards them to `log` as long as no
+/// `tracing` subscriber is set, so that each event is emitted exactly once.
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "log")] {
+/// use std::sync::Mutex;
+/// use try_or_wrap_s::{try_or_wrap, try_or_wrap_opt};
+///
+/// static RECORDS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+///
+/// struct Capture;
+/// impl log::Log for Capture {
+///     fn enabled(&self, _metadata: &log::Metadata) -> bool {
+///         true
+///     }
+///     fn log(&self, record: &log::Record) {
+///         RECORDS.lock().unwrap().push((record.level(), record.args().to_string()));
+///     }
+///     fn flush(&self) {}
+/// }
+/// log::set_logger(&Capture).unwrap();
+/// log::set_max_level(log::LevelFilter::Trace);
+///
+/// #[derive(Debug)]
+/// struct InvalidInput(&'static str);
+///
+/// fn parse(input: &'static str) -> Result<Result<u32, InvalidInput>, ()> {
+///     let parsed = try_or_wrap!(input.parse().map_err(|_| InvalidInput(input)), Ok);
+///     let doubled = try_or_wrap_opt!(u32::checked_mul(parsed, 2), Ok, else = InvalidInput(input), log = warn);
+///     Ok(Ok(doubled))
+/// }
+///
+/// assert!(parse("1").unwrap().is_ok());
+/// assert!(parse("a").unwrap().is_err());
+/// assert!(parse("4000000000").unwrap().is_err());
+/// let records = RECORDS.lock().unwrap();
+/// assert_eq!(records.len(), 2);
+/// assert_eq!(records[0].0, log::Level::Debug);
+/// assert!(records[0].1.contains(r#"Err(InvalidInput("a"))"#));
+/// assert_eq!(records[1].0, log::Level::Warn);
+/// assert!(records[1].1.contains(r#"Err(InvalidInput("4000000000"))"#));
+/// # }
+/// ```
+///
+/// With `tracing`:
+/// ```
+/// # #[cfg(feature = "tracing")] {
+/// use std::{fmt, sync::{Arc, Mutex}};
+/// use tracing::{field::{Field, Visit}, span, Event, Level, Metadata, Subscriber};
+/// use try_or_wrap_s::{set_log_level, try_or_wrap, LogLevel};
+///
+/// #[derive(Default)]
+/// struct Fields(Vec<(&'static str, String)>);
+/// impl Visit for Fields {
+///     fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+///         self.0.push((field.name(), format!("{:?}", value)));
+///     }
+/// }
+///
+/// #[derive(Clone, Default)]
+/// struct Capture(Arc<Mutex<Vec<(Level, Fields)>>>);
+/// impl Subscriber for Capture {
+///     fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+///         true
+///     }
+///     fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+///         span::Id::from_u64(1)
+///     }
+///     fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+///     fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+///     fn event(&self, event: &Event<'_>) {
+///         let mut fields = Fields::default();
+///         event.record(&mut fields);
+///         self.0.lock().unwrap().push((*event.metadata().level(), fields));
+///     }
+///     fn enter(&self, _span: &span::Id) {}
+///     fn exit(&self, _span: &span::Id) {}
+/// }
+///
+/// fn parse(input: &str) -> Result<Result<u32, std::num::ParseIntError>, ()> {
+///     Ok(Ok(try_or_wrap!(input.parse::<u32>(), Ok)))
+/// }
+///
+/// set_log_level(LogLevel::Info);
+/// let capture = Capture::default();
+/// tracing::subscriber::with_default(capture.clone(), || {
+///     assert!(parse("1").unwrap().is_ok());
+///     assert!(parse("a").unwrap().is_err());
+/// });
+/// let events = capture.0.lock().unwrap();
+/// assert_eq!(events.len(), 1);
+/// let (level, fields) = &events[0];
+/// assert_eq!(*level, Level::INFO);
+/// let field = |name| fields.0.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str());
+/// assert_eq!(field("error"), Some("Err(ParseIntError { kind: InvalidDigit })"));
+/// assert_eq!(field("wrapper"), Some("\"Ok\""));
+/// assert_eq!(field("call_site"), Some(&*format!("{}:{}:{}", file!(), line!() - 16, 11)));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Debug as u8);
+
+/// Sets the level of the events of invocations that don't specify `log = <level>`
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Level of the events of invocations that don't specify `log = <level>`
+pub fn log_level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        1 => LogLevel::Error,
+        2 => LogLevel::Warn,
+        3 => LogLevel::Info,
+        4 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
+}
+
+/// Early return of a `try_or_wrap!` invocation, as built by the macros
+pub struct BreakEvent<'a> {
+    /// `None` if the residual doesn't implement `Debug`
+    pub error: Option<&'a dyn fmt::Debug>,
+    pub level: Option<LogLevel>,
+    pub wrapper: &'static str,
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Gives the `Debug` of the residual when it has one, through autoref specialization: the
+/// macros call `(&BreakRef(&residual)).debug_residual()`, which resolves to [`DebugResidual`] if
+/// possible, and to [`NoDebugResidual`] (that requires one more autoref) otherwise.
+pub struct BreakRef<'a, T>(pub &'a T);
+
+pub trait DebugResidual {
+    fn debug_residual(&self) -> Option<&dyn fmt::Debug>;
+}
+
+impl<T: fmt::Debug> DebugResidual for BreakRef<'_, T> {
+    fn debug_residual(&self) -> Option<&dyn fmt::Debug> {
+        Some(self.0)
+    }
+}
+
+pub trait NoDebugResidual {
+    fn debug_residual(&self) -> Option<&dyn fmt::Debug>;
+}
+
+impl<T> NoDebugResidual for &BreakRef<'_, T> {
+    fn debug_residual(&self) -> Option<&dyn fmt::Debug> {
+        None
+    }
+}
+
+struct ErrorDebug<'a>(Option<&'a dyn fmt::Debug>);
+
+impl fmt::Debug for ErrorDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(error) => error.fmt(f),
+            None => f.write_str("<no Debug impl>"),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+struct CallSite<'a>(&'a BreakEvent<'a>);
+
+#[cfg(feature = "tracing")]
+impl fmt::Display for CallSite<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.0.file, self.0.line, self.0.column)
+    }
+}
+
+pub fn log_break(event: &BreakEvent<'_>) {
+    let level = event.level.unwrap_or_else(log_level);
+    #[cfg(all(feature = "log", not(feature = "tracing")))]
+    emit_log(level, event);
+    #[cfg(feature = "tracing")]
+    emit_tracing(level, event);
+}
+
+#[cfg(all(feature = "log", not(feature = "tracing")))]
+fn emit_log(level: LogLevel, event: &BreakEvent<'_>) {
+    let level = match level {
+        LogLevel::Error => log::Level::Error,
+        LogLevel::Warn => log::Level::Warn,
+        LogLevel::Info => log::Level::Info,
+        LogLevel::Debug => log::Level::Debug,
+        LogLevel::Trace => log::Level::Trace,
+    };
+    if level > log::max_level() {
+        return;
+    }
+    let metadata = log::Metadata::builder()
+        .level(level)
+        .target(event.module_path)
+        .build();
+    let logger = log::logger();
+    if logger.enabled(&metadata) {
+        logger.log(
+            &log::Record::builder()
+                .metadata(metadata)
+                .module_path_static(Some(event.module_path))
+                .file_static(Some(event.file))
+                .line(Some(event.line))
+                .args(format_args!(
+                    "returning early with {}: {:?}",
+                    event.wrapper,
+                    ErrorDebug(event.error)
+                ))
+                .build(),
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn emit_tracing(level: LogLevel, event: &BreakEvent<'_>) {
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(
+                target: "try_or_wrap_s",
+                $level,
+                error = ?ErrorDebug(event.error),
+                wrapper = event.wrapper,
+                call_site = %CallSite(event),
+                module_path = event.module_path,
+                "returning early"
+            )
+        };
+    }
+    match level {
+        LogLevel::Error => emit!(tracing::Level::ERROR),
+        LogLevel::Warn => emit!(tracing::Level::WARN),
+        LogLevel::Info => emit!(tracing::Level::INFO),
+        LogLevel::Debug => emit!(tracing::Level::DEBUG),
+        LogLevel::Trace => emit!(tracing::Level::TRACE),
+    }
+}
@@ -0,0 +1,124 @@
+/// Combinators for nested results (`Result<Result<T, E>, F>`) outside of early-return contexts
+///
+/// # Example
+/// ```
+/// use try_or_wrap_s::NestedResultExt;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct InvalidInputError;
+/// #[derive(Debug, PartialEq)]
+/// struct DatabaseError;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum AnyError {
+///     InvalidInput(InvalidInputError),
+///     Database(DatabaseError),
+/// }
+/// impl From<InvalidInputError> for AnyError {
+///     fn from(err: InvalidInputError) -> Self {
+///         AnyError::InvalidInput(err)
+///     }
+/// }
+/// impl From<DatabaseError> for AnyError {
+///     fn from(err: DatabaseError) -> Self {
+///         AnyError::Database(err)
+///     }
+/// }
+///
+/// fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+///     match input {
+///         i32::MIN..=-1 => Ok(Err(InvalidInputError)),
+///         0 => Err(DatabaseError),
+///         _ => Ok(Ok(input as u32)),
+///     }
+/// }
+///
+/// assert_eq!(validate_input_with_database(21).map_inner(|v| v * 2), Ok(Ok(42)));
+/// assert_eq!(validate_input_with_database(-1).map_inner(|v| v * 2), Ok(Err(InvalidInputError)));
+/// assert_eq!(validate_input_with_database(0).map_inner(|v| v * 2), Err(DatabaseError));
+///
+/// assert_eq!(validate_input_with_database(-1).map_inner_err(|_| "invalid"), Ok(Err("invalid")));
+///
+/// let halve = |v: u32| if v % 2 == 0 { Ok(v / 2) } else { Err(InvalidInputError) };
+/// assert_eq!(validate_input_with_database(42).and_then_inner(halve), Ok(Ok(21)));
+/// assert_eq!(validate_input_with_database(21).and_then_inner(halve), Ok(Err(InvalidInputError)));
+/// assert_eq!(validate_input_with_database(0).and_then_inner(halve), Err(DatabaseError));
+///
+/// assert_eq!(validate_input_with_database(21).transpose_layers(), Ok(Ok(21)));
+/// assert_eq!(validate_input_with_database(-1).transpose_layers(), Err(InvalidInputError));
+/// assert_eq!(validate_input_with_database(0).transpose_layers(), Ok(Err(DatabaseError)));
+///
+/// assert_eq!(validate_input_with_database(21).flatten_into::<AnyError>(), Ok(21));
+/// assert_eq!(
+///     validate_input_with_database(-1).flatten_into::<AnyError>(),
+///     Err(AnyError::InvalidInput(InvalidInputError)),
+/// );
+/// assert_eq!(
+///     validate_input_with_database(0).flatten_into::<AnyError>(),
+///     Err(AnyError::Database(DatabaseError)),
+/// );
+///
+/// assert_eq!(validate_input_with_database(21).inner_ok(), Ok(Some(21)));
+/// assert_eq!(validate_input_with_database(-1).inner_ok(), Ok(None));
+/// assert_eq!(validate_input_with_database(0).inner_ok(), Err(DatabaseError));
+/// ```
+pub trait NestedResultExt<T, E, F> {
+    /// Maps the inner `Ok` value, leaving both errors untouched
+    fn map_inner<U>(self, f: impl FnOnce(T) -> U) -> Result<Result<U, E>, F>;
+
+    /// Maps the inner error, leaving the rest untouched
+    fn map_inner_err<E2>(self, f: impl FnOnce(E) -> E2) -> Result<Result<T, E2>, F>;
+
+    /// Calls `f` with the inner `Ok` value, or propagates the error
+    fn and_then_inner<U>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Result<U, E>, F>;
+
+    /// Swaps the inner and outer error layers
+    fn transpose_layers(self) -> Result<Result<T, F>, E>;
+
+    /// Flattens into a single `Result`, converting both errors into a common error type
+    fn flatten_into<G>(self) -> Result<T, G>
+    where
+        E: Into<G>,
+        F: Into<G>;
+
+    /// Discards the inner error, keeping only the outer one
+    fn inner_ok(self) -> Result<Option<T>, F>;
+}
+
+impl<T, E, F> NestedResultExt<T, E, F> for Result<Result<T, E>, F> {
+    fn map_inner<U>(self, f: impl FnOnce(T) -> U) -> Result<Result<U, E>, F> {
+        self.map(|inner| inner.map(f))
+    }
+
+    fn map_inner_err<E2>(self, f: impl FnOnce(E) -> E2) -> Result<Result<T, E2>, F> {
+        self.map(|inner| inner.map_err(f))
+    }
+
+    fn and_then_inner<U>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Result<U, E>, F> {
+        self.map(|inner| inner.and_then(f))
+    }
+
+    fn transpose_layers(self) -> Result<Result<T, F>, E> {
+        match self {
+            Ok(Ok(val)) => Ok(Ok(val)),
+            Ok(Err(err)) => Err(err),
+            Err(fault) => Ok(Err(fault)),
+        }
+    }
+
+    fn flatten_into<G>(self) -> Result<T, G>
+    where
+        E: Into<G>,
+        F: Into<G>,
+    {
+        match self {
+            Ok(Ok(val)) => Ok(val),
+            Ok(Err(err)) => Err(err.into()),
+            Err(fault) => Err(fault.into()),
+        }
+    }
+
+    fn inner_ok(self) -> Result<Option<T>, F> {
+        self.map(Result::ok)
+    }
+}
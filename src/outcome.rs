@@ -0,0 +1,161 @@
+use core::convert::Infallible;
+use core::ops::ControlFlow;
+
+use crate::branch::{FromMappedError, MappedBreak};
+use crate::{FromBreak, FromError, TryOrWrap};
+
+/// Three-state alternative to `Result<Result<T, E>, F>`
+///
+/// `Failure` holds the inner error (e.g. invalid input), and `Fault` the outer one
+/// (e.g. database error). It converts losslessly to and from the nested `Result`.
+///
+/// `try_or_wrap!` can return into an `Outcome`: an inner error (`Result<_, E>`) is returned as
+/// `Failure`, and an `Outcome` is returned as-is. As the `Outcome` is the return type itself,
+/// the wrapper is `identity`:
+///
+/// ```
+/// use std::convert::identity;
+/// use try_or_wrap_s::{try_or_wrap, Outcome};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct InvalidInputError;
+/// #[derive(Debug, PartialEq)]
+/// struct DatabaseError;
+///
+/// fn validate_input_with_database(input: i32) -> Result<Result<u32, InvalidInputError>, DatabaseError> {
+///     match input {
+///         i32::MIN..=-1 => Ok(Err(InvalidInputError)),
+///         0 => Err(DatabaseError),
+///         _ => Ok(Ok(input as u32)),
+///     }
+/// }
+///
+/// fn foo(input: i32) -> Outcome<u32, InvalidInputError, DatabaseError> {
+///     let validated_input = try_or_wrap!(Outcome::from(validate_input_with_database(input)), identity);
+///     let tripled = try_or_wrap!(validated_input.checked_mul(3).ok_or(InvalidInputError), identity);
+///     Outcome::Success(tripled)
+/// }
+///
+/// assert_eq!(foo(21), Outcome::Success(63));
+/// assert_eq!(foo(-1), Outcome::Failure(InvalidInputError));
+/// assert_eq!(foo(i32::MAX), Outcome::Failure(InvalidInputError));
+/// assert_eq!(foo(0), Outcome::Fault(DatabaseError));
+/// assert_eq!(Result::from(foo(0)), Err(DatabaseError));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome<T, E, F> {
+    /// The operation succeeded
+    Success(T),
+    /// The operation failed with an inner error (the `Ok(Err(_))` of the nested `Result`)
+    Failure(E),
+    /// The operation failed with an outer error (the `Err(_)` of the nested `Result`)
+    Fault(F),
+}
+
+impl<T, E, F> Outcome<T, E, F> {
+    /// Maps the `Success` value, leaving errors untouched
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Outcome<U, E, F> {
+        match self {
+            Outcome::Success(val) => Outcome::Success(f(val)),
+            Outcome::Failure(err) => Outcome::Failure(err),
+            Outcome::Fault(fault) => Outcome::Fault(fault),
+        }
+    }
+
+    /// Maps the `Failure` error, leaving the rest untouched
+    pub fn map_failure<E2>(self, f: impl FnOnce(E) -> E2) -> Outcome<T, E2, F> {
+        match self {
+            Outcome::Success(val) => Outcome::Success(val),
+            Outcome::Failure(err) => Outcome::Failure(f(err)),
+            Outcome::Fault(fault) => Outcome::Fault(fault),
+        }
+    }
+
+    /// Maps the `Fault` error, leaving the rest untouched
+    pub fn map_fault<F2>(self, f: impl FnOnce(F) -> F2) -> Outcome<T, E, F2> {
+        match self {
+            Outcome::Success(val) => Outcome::Success(val),
+            Outcome::Failure(err) => Outcome::Failure(err),
+            Outcome::Fault(fault) => Outcome::Fault(f(fault)),
+        }
+    }
+
+    /// Calls `f` with the `Success` value, or propagates the error
+    pub fn and_then<U>(self, f: impl FnOnce(T) -> Outcome<U, E, F>) -> Outcome<U, E, F> {
+        match self {
+            Outcome::Success(val) => f(val),
+            Outcome::Failure(err) => Outcome::Failure(err),
+            Outcome::Fault(fault) => Outcome::Fault(fault),
+        }
+    }
+}
+
+impl<T, E, F> From<Result<Result<T, E>, F>> for Outcome<T, E, F> {
+    fn from(result: Result<Result<T, E>, F>) -> Self {
+        match result {
+            Ok(Ok(val)) => Outcome::Success(val),
+            Ok(Err(err)) => Outcome::Failure(err),
+            Err(fault) => Outcome::Fault(fault),
+        }
+    }
+}
+
+impl<T, E, F> From<Outcome<T, E, F>> for Result<Result<T, E>, F> {
+    fn from(outcome: Outcome<T, E, F>) -> Self {
+        match outcome {
+            Outcome::Success(val) => Ok(Ok(val)),
+            Outcome::Failure(err) => Ok(Err(err)),
+            Outcome::Fault(fault) => Err(fault),
+        }
+    }
+}
+
+impl<T, E, F> TryOrWrap for Outcome<T, E, F> {
+    type Continue = T;
+    type Break = Outcome<Infallible, E, F>;
+    fn branch(self) -> ControlFlow<Self::Break, Self::Continue> {
+        match self {
+            Outcome::Success(val) => ControlFlow::Continue(val),
+            Outcome::Failure(err) => ControlFlow::Break(Outcome::Failure(err)),
+            Outcome::Fault(fault) => ControlFlow::Break(Outcome::Fault(fault)),
+        }
+    }
+}
+
+impl<T, E, F, E2, F2, M> FromBreak<Outcome<Infallible, E, F>, M> for Outcome<T, E2, F2>
+where
+    E2: FromError<E, M>,
+    F2: From<F>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: Outcome<Infallible, E, F>) -> Self {
+        match residual {
+            Outcome::Success(never) => match never {},
+            Outcome::Failure(err) => Outcome::Failure(E2::from_error(err)),
+            Outcome::Fault(fault) => Outcome::Fault(F2::from(fault)),
+        }
+    }
+}
+
+impl<T, E, F, E2, M> FromBreak<Result<Infallible, E>, M> for Outcome<T, E2, F>
+where
+    E2: FromError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: Result<Infallible, E>) -> Self {
+        match residual {
+            Ok(never) => match never {},
+            Err(err) => Outcome::Failure(E2::from_error(err)),
+        }
+    }
+}
+
+impl<T, E, F, E2, M> FromBreak<MappedBreak<E>, M> for Outcome<T, E2, F>
+where
+    E2: FromMappedError<E, M>,
+{
+    #[cfg_attr(feature = "track-location", track_caller)]
+    fn from_break(residual: MappedBreak<E>) -> Self {
+        Outcome::Failure(E2::from_mapped_error(residual.0))
+    }
+}
@@ -0,0 +1,166 @@
+/// Inner error that can be rendered as an HTTP client error response by [`NestedResponse`]
+pub trait ClientError {
+    /// Status code of the response
+    ///
+    /// Status codes that aren't client errors (4xx) are rendered as `400 Bad Request`.
+    fn status(&self) -> u16;
+
+    /// Body of the response
+    fn body(&self) -> String;
+}
+
+/// HTTP response for a nested result (`Result<Result<T, E>, F>`)
+///
+/// - `Ok(Ok(val))` is rendered as `val`,
+/// - `Ok(Err(err))` is rendered as a client error according to its [`ClientError`] impl,
+/// - `Err(_)` is rendered as `500 Internal Server Error`, without leaking the error in the body.
+///
+/// It implements `axum`'s `IntoResponse` with the `axum` feature, and `actix-web`'s `Responder`
+/// with the `actix-web` feature.
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "axum")] {
+/// use axum::{body::Body, handler::Handler, http::{Request, StatusCode}};
+/// use try_or_wrap_s::{try_or_wrap, ClientError, NestedResponse};
+/// # use try_or_wrap_s::__test_support::block_on;
+///
+/// struct NotFound(u32);
+/// impl ClientError for NotFound {
+///     fn status(&self) -> u16 {
+///         404
+///     }
+///     fn body(&self) -> String {
+///         format!("user {} not found", self.0)
+///     }
+/// }
+/// struct DbError;
+///
+/// fn find_user(id: u32) -> Result<Result<String, NotFound>, DbError> {
+///     match id {
+///         0 => Err(DbError),
+///         1 => Ok(Ok("Ferris".to_owned())),
+///         _ => Ok(Err(NotFound(id))),
+///     }
+/// }
+///
+/// fn greet(id: u32) -> Result<Result<String, NotFound>, DbError> {
+///     let name = try_or_wrap!(find_user(id)?, Ok);
+///     Ok(Ok(format!("Hello, {}!", name)))
+/// }
+///
+/// fn call(handler: impl Handler<((),), ()>) -> (StatusCode, String) {
+///     let response = block_on(handler.call(Request::new(Body::empty()), ()));
+///     let status = response.status();
+///     let body = block_on(axum::body::to_bytes(response.into_body(), usize::MAX)).unwrap();
+///     (status, String::from_utf8(body.to_vec()).unwrap())
+/// }
+///
+/// let ok = || async { NestedResponse::from(greet(1)) };
+/// assert_eq!(call(ok), (StatusCode::OK, "Hello, Ferris!".to_owned()));
+/// let not_found = || async { NestedResponse::from(greet(2)) };
+/// assert_eq!(call(not_found), (StatusCode::NOT_FOUND, "user 2 not found".to_owned()));
+/// let db_error = || async { NestedResponse::from(greet(0)) };
+/// assert_eq!(call(db_error).0, StatusCode::INTERNAL_SERVER_ERROR);
+/// # }
+/// ```
+///
+/// With `actix-web`:
+/// ```
+/// # #[cfg(feature = "actix-web")] {
+/// use actix_web::{http::StatusCode, test, web, App};
+/// use try_or_wrap_s::{ClientError, NestedResponse};
+///
+/// struct Forbidden;
+/// impl ClientError for Forbidden {
+///     fn status(&self) -> u16 {
+///         403
+///     }
+///     fn body(&self) -> String {
+///         "forbidden".to_owned()
+///     }
+/// }
+/// struct DbError;
+///
+/// async fn handler(path: web::Path<u32>) -> NestedResponse<&'static str, Forbidden, DbError> {
+///     NestedResponse(match path.into_inner() {
+///         0 => Err(DbError),
+///         1 => Ok(Ok("welcome")),
+///         _ => Ok(Err(Forbidden)),
+///     })
+/// }
+///
+/// actix_web::rt::System::new().block_on(async {
+///     let app = test::init_service(App::new().route("/{id}", web::get().to(handler))).await;
+///     for (uri, status, body) in [
+///         ("/1", StatusCode::OK, "welcome"),
+///         ("/2", StatusCode::FORBIDDEN, "forbidden"),
+///         ("/0", StatusCode::INTERNAL_SERVER_ERROR, ""),
+///     ] {
+///         let response = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+///         assert_eq!(response.status(), status);
+///         assert_eq!(test::read_body(response).await, body.as_bytes());
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NestedResponse<T, E, F>(pub Result<Result<T, E>, F>);
+
+impl<T, E, F> From<Result<Result<T, E>, F>> for NestedResponse<T, E, F> {
+    fn from(result: Result<Result<T, E>, F>) -> Self {
+        Self(result)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<T, E, F> axum::response::IntoResponse for NestedResponse<T, E, F>
+where
+    T: axum::response::IntoResponse,
+    E: ClientError,
+{
+    fn into_response(self) -> axum::response::Response {
+        use axum::http::StatusCode;
+
+        match self.0 {
+            Ok(Ok(val)) => val.into_response(),
+            Ok(Err(err)) => {
+                let status = StatusCode::from_u16(err.status())
+                    .ok()
+                    .filter(StatusCode::is_client_error)
+                    .unwrap_or(StatusCode::BAD_REQUEST);
+                (status, err.body()).into_response()
+            }
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+impl<T, E, F> actix_web::Responder for NestedResponse<T, E, F>
+where
+    T: actix_web::Responder,
+    E: ClientError,
+{
+    type Body = actix_web::body::EitherBody<T::Body>;
+
+    fn respond_to(self, req: &actix_web::HttpRequest) -> actix_web::HttpResponse<Self::Body> {
+        use actix_web::{http::StatusCode, HttpResponse};
+
+        match self.0 {
+            Ok(Ok(val)) => val.respond_to(req).map_into_left_body(),
+            Ok(Err(err)) => {
+                let status = StatusCode::from_u16(err.status())
+                    .ok()
+                    .filter(StatusCode::is_client_error)
+                    .unwrap_or(StatusCode::BAD_REQUEST);
+                HttpResponse::build(status)
+                    .body(err.body())
+                    .map_into_right_body()
+            }
+            Err(_) => HttpResponse::InternalServerError()
+                .finish()
+                .map_into_right_body(),
+        }
+    }
+}
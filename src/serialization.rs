@@ -0,0 +1,89 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes nested results (`Result<Result<T, E>, F>`) as a tagged three-way outcome
+///
+/// Use it with `#[serde(with = "NestedResultSerde")]`. The format is
+/// `{"status": "ok", "value": val}` for `Ok(Ok(val))`, `{"status": "invalid", "value": err}` for
+/// `Ok(Err(err))` and `{"status": "error", "value": fault}` for `Err(fault)`.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use try_or_wrap_s::NestedResultSerde;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Response {
+///     #[serde(with = "NestedResultSerde")]
+///     result: Result<Result<u32, String>, String>,
+/// }
+///
+/// for (result, json) in [
+///     (Ok(Ok(42)), r#"{"result":{"status":"ok","value":42}}"#),
+///     (Ok(Err("invalid input".to_owned())), r#"{"result":{"status":"invalid","value":"invalid input"}}"#),
+///     (Err("database error".to_owned()), r#"{"result":{"status":"error","value":"database error"}}"#),
+/// ] {
+///     let response = Response { result };
+///     assert_eq!(serde_json::to_string(&response).unwrap(), json);
+///     assert_eq!(serde_json::from_str::<Response>(json).unwrap(), response);
+/// }
+///
+/// // The fields may come in any order
+/// assert_eq!(
+///     serde_json::from_str::<Response>(r#"{"result":{"value":1,"status":"ok"}}"#).unwrap(),
+///     Response { result: Ok(Ok(1)) },
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct NestedResultSerde;
+
+#[derive(Serialize)]
+#[serde(tag = "status", content = "value", rename_all = "lowercase")]
+enum NestedResultRef<'a, T, E, F> {
+    Ok(&'a T),
+    Invalid(&'a E),
+    Error(&'a F),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "status", content = "value", rename_all = "lowercase")]
+enum NestedResult<T, E, F> {
+    Ok(T),
+    Invalid(E),
+    Error(F),
+}
+
+impl NestedResultSerde {
+    pub fn serialize<T, E, F, S>(
+        result: &Result<Result<T, E>, F>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        E: Serialize,
+        F: Serialize,
+        S: Serializer,
+    {
+        match result {
+            Ok(Ok(val)) => NestedResultRef::Ok(val),
+            Ok(Err(err)) => NestedResultRef::Invalid(err),
+            Err(fault) => NestedResultRef::Error(fault),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, E, F, D>(
+        deserializer: D,
+    ) -> Result<Result<Result<T, E>, F>, D::Error>
+    where
+        T: Deserialize<'de>,
+        E: Deserialize<'de>,
+        F: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(match NestedResult::deserialize(deserializer)? {
+            NestedResult::Ok(val) => Ok(Ok(val)),
+            NestedResult::Invalid(err) => Ok(Err(err)),
+            NestedResult::Error(fault) => Err(fault),
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::backtrace::Backtrace;
+use std::fmt;
+
+use crate::branch::FromMappedError;
+use crate::FromError;
+
+/// Error along with the backtrace of the `try_or_wrap!` invocation that returned it
+///
+/// Use `Traced<E>` as the inner error type of the return type: `try_or_wrap!` converts the error
+/// into `E` with `From` (or with `map_err`), then captures a [`Backtrace`]. As with [`Backtrace::capture`], it is only
+/// actually captured if the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variable is set.
+///
+/// # Example
+/// ```
+/// use std::{backtrace::BacktraceStatus, fmt, num::ParseIntError};
+/// use try_or_wrap_s::{try_or_wrap, Traced};
+///
+/// #[derive(Debug)]
+/// struct ConfigError(ParseIntError);
+///
+/// impl fmt::Display for ConfigError {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         write!(f, "invalid number: {}", self.0)
+///     }
+/// }
+///
+/// impl From<ParseIntError> for ConfigError {
+///     fn from(err: ParseIntError) -> Self {
+///         ConfigError(err)
+///     }
+/// }
+///
+/// fn parse(input: &str) -> Result<Result<u32, Traced<ConfigError>>, std::io::Error> {
+///     let parsed = try_or_wrap!(input.parse::<u32>(), Ok);
+///     Ok(Ok(parsed))
+/// }
+///
+/// std::env::set_var("RUST_BACKTRACE", "1");
+/// let err = parse("a").unwrap().unwrap_err();
+/// assert_eq!(err.backtrace().status(), BacktraceStatus::Captured);
+/// assert_eq!(err.to_string(), "invalid number: invalid digit found in string");
+/// ```
+#[derive(Debug)]
+pub struct Traced<E> {
+    error: E,
+    backtrace: Backtrace,
+}
+
+impl<E> Traced<E> {
+    /// Wraps `error`, capturing a backtrace if enabled by the environment
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+/// Marker of the [`FromError`] implementation of `Traced`
+#[doc(hidden)]
+pub enum Trace {}
+
+impl<E, E2> FromError<E, Trace> for Traced<E2>
+where
+    E2: From<E>,
+{
+    fn from_error(err: E) -> Self {
+        Self::new(E2::from(err))
+    }
+}
+
+impl<E> FromMappedError<E, Trace> for Traced<E> {
+    fn from_mapped_error(err: E) -> Self {
+        Self::new(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Traced<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<E> std::error::Error for Traced<E>
+where
+    E: std::error::Error,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
@@ -0,0 +1,125 @@
+/// Runs `f` in a transaction that is committed unless `f` returns an outer error
+///
+/// Unlike the usual closure-based transaction APIs that roll back on any `Err`, an inner error
+/// (`Ok(Err(E))`) is a regular outcome: what was done before it (e.g. writing an audit log)
+/// is committed. Only an outer error (`Err(F)`) rolls the transaction back.
+///
+/// `begin` gives the transaction that is passed to `f`, then to `commit` or `rollback`. If any of
+/// these three fails, its error is returned as an outer error (after conversion with `From`).
+///
+/// See [`NestedTransaction`] for an example.
+pub fn transaction_nested<Tx, T, E, F, G>(
+    begin: impl FnOnce() -> Result<Tx, G>,
+    commit: impl FnOnce(Tx) -> Result<(), G>,
+    rollback: impl FnOnce(Tx) -> Result<(), G>,
+    f: impl FnOnce(&mut Tx) -> Result<Result<T, E>, F>,
+) -> Result<Result<T, E>, F>
+where
+    F: From<G>,
+{
+    let mut tx = begin()?;
+    match f(&mut tx) {
+        Ok(result) => {
+            commit(tx)?;
+            Ok(result)
+        }
+        Err(fault) => {
+            rollback(tx)?;
+            Err(fault)
+        }
+    }
+}
+
+/// Connection that supports transactions committed on inner errors and rolled back on outer ones
+///
+/// # Example
+/// ```
+/// use try_or_wrap_s::{try_or_wrap, NestedTransaction};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Forbidden;
+/// #[derive(Debug, PartialEq)]
+/// struct DbError;
+///
+/// /// In-memory fake connection
+/// #[derive(Default)]
+/// struct Connection {
+///     committed: Vec<String>,
+///     pending: Option<Vec<String>>,
+/// }
+///
+/// impl Connection {
+///     fn insert(&mut self, row: &str) -> Result<(), DbError> {
+///         self.pending.as_mut().ok_or(DbError)?.push(row.to_owned());
+///         Ok(())
+///     }
+/// }
+///
+/// impl NestedTransaction for Connection {
+///     type Error = DbError;
+///     fn begin(&mut self) -> Result<(), DbError> {
+///         self.pending = Some(Vec::new());
+///         Ok(())
+///     }
+///     fn commit(&mut self) -> Result<(), DbError> {
+///         let pending = self.pending.take().ok_or(DbError)?;
+///         self.committed.extend(pending);
+///         Ok(())
+///     }
+///     fn rollback(&mut self) -> Result<(), DbError> {
+///         self.pending.take().ok_or(DbError)?;
+///         Ok(())
+///     }
+/// }
+///
+/// fn check_allowed(user: &str) -> Result<(), Forbidden> {
+///     if user == "admin" { Ok(()) } else { Err(Forbidden) }
+/// }
+///
+/// fn delete_everything(conn: &mut Connection, user: &str) -> Result<Result<(), Forbidden>, DbError> {
+///     conn.transaction_nested(|conn| {
+///         conn.insert(&format!("audit: {} deletes everything", user))?;
+///         try_or_wrap!(check_allowed(user), Ok);
+///         if user == "admin" {
+///             conn.insert("tombstone")?;
+///             // Fails after the inserts: the whole transaction is rolled back
+///             return Err(DbError);
+///         }
+///         Ok(Ok(()))
+///     })
+/// }
+///
+/// let mut conn = Connection::default();
+/// assert_eq!(delete_everything(&mut conn, "guest"), Ok(Err(Forbidden)));
+/// assert_eq!(conn.committed, ["audit: guest deletes everything"]);
+/// assert_eq!(delete_everything(&mut conn, "admin"), Err(DbError));
+/// assert_eq!(conn.committed, ["audit: guest deletes everything"]);
+/// ```
+pub trait NestedTransaction {
+    type Error;
+
+    fn begin(&mut self) -> Result<(), Self::Error>;
+
+    fn commit(&mut self) -> Result<(), Self::Error>;
+
+    fn rollback(&mut self) -> Result<(), Self::Error>;
+
+    /// Runs `f` in a transaction that is committed unless `f` returns an outer error
+    ///
+    /// See [`transaction_nested`].
+    fn transaction_nested<T, E, F>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Result<T, E>, F>,
+    ) -> Result<Result<T, E>, F>
+    where
+        F: From<Self::Error>,
+        Self: Sized,
+    {
+        transaction_nested(
+            move || self.begin().map(|()| self),
+            |tx| tx.commit(),
+            |tx| tx.rollback(),
+            |tx| f(tx),
+        )
+    }
+}
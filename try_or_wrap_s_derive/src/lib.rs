@@ -0,0 +1,344 @@
+//! Procedural macros for the `try_or_wrap_s` crate.
+//!
+//! This crate is not meant to be used directly: use the re-exports from `try_or_wrap_s` instead.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Function attribute that turns `expr??` into `try_or_wrap!(expr?)` in the function body
+///
+/// The first `?` propagates the outer error as usual, and the second one returns the inner
+/// error wrapped in `Ok` (or in the wrapper given as attribute argument, e.g.
+/// `#[try_or_wrap_fn(MyWrapper)]`).
+///
+/// # Example
+/// ```ignore
+/// #[try_or_wrap_fn]
+/// fn foo(input: Input) -> Result<Result<FinalOutput, InvalidInputError>, DatabaseError> {
+///     let validated_input: ValidatedInput = validate_input_with_database(input)??;
+///     Ok(Ok(do_stuff_with_validated_input(validated_input)?))
+/// }
+/// ```
+///
+/// The expression before `??` has to be a method call/field access/path/indexing chain (e.g.
+/// `self.db.get(id).await??`). Other expressions (`match`, blocks, binary operations...) need
+/// to be wrapped in parentheses.
+/// ```
+/// use std::{collections::HashMap, str::FromStr};
+/// use try_or_wrap_s::try_or_wrap_fn;
+/// # use try_or_wrap_s::__test_support::block_on;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct NotFound;
+/// #[derive(Debug, PartialEq)]
+/// struct DbError;
+///
+/// struct Db(HashMap<u32, &'static str>);
+///
+/// impl Db {
+///     async fn get(&self, id: u32) -> Result<Result<&'static str, NotFound>, DbError> {
+///         Ok(self.0.get(&id).copied().ok_or(NotFound))
+///     }
+/// }
+///
+/// fn parse<T: FromStr>(value: &str) -> Result<Result<T, NotFound>, DbError> {
+///     Ok(value.parse().map_err(|_| NotFound))
+/// }
+///
+/// macro_rules! checked {
+///     ($value:expr) => {
+///         if $value == 0 { Err(DbError) } else { Ok(Ok($value)) }
+///     };
+/// }
+///
+/// #[try_or_wrap_fn]
+/// async fn amount(db: &Db, id: u32) -> Result<Result<u64, NotFound>, DbError> {
+///     let value = db.get(id).await??;
+///     let amount = parse::<u64>(value)??;
+///     Ok(Ok(checked!(amount)??))
+/// }
+///
+/// let db = Db([(1, "42"), (2, "invalid"), (3, "0")].iter().copied().collect());
+/// assert_eq!(block_on(amount(&db, 1)), Ok(Ok(42)));
+/// assert_eq!(block_on(amount(&db, 2)), Ok(Err(NotFound)));
+/// assert_eq!(block_on(amount(&db, 4)), Ok(Err(NotFound)));
+/// assert_eq!(block_on(amount(&db, 3)), Err(DbError));
+///
+/// // The arguments of the attribute are given to `try_or_wrap!` after the expression
+/// #[try_or_wrap_fn(depth = 2)]
+/// fn nested(id: u32) -> Result<Result<Result<u32, NotFound>, ()>, DbError> {
+///     Ok(Ok(Ok(parse::<u32>(&id.to_string())??)))
+/// }
+///
+/// assert_eq!(nested(7), Ok(Ok(Ok(7))));
+/// ```
+///
+/// The rewritten code calls `::try_or_wrap_s::try_or_wrap!`: the attribute doesn't work if the
+/// `try_or_wrap_s` dependency is renamed in `Cargo.toml` (e.g. with
+/// `wrap = { package = "try_or_wrap_s", ... }`).
+///
+/// Expressions that aren't such a chain are rejected:
+/// ```compile_fail
+/// use try_or_wrap_s::try_or_wrap_fn;
+///
+/// #[try_or_wrap_fn]
+/// async fn foo() -> Result<Result<u32, ()>, ()> {
+///     let value = async { Ok(Ok(1)) }.await??;
+///     Ok(Ok(value))
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn try_or_wrap_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let wrapper: Vec<TokenTree> = attr.into_iter().collect();
+    let mut tokens: Vec<TokenTree> = item.into_iter().collect();
+
+    let is_fn = tokens
+        .iter()
+        .any(|tt| matches!(tt, TokenTree::Ident(ident) if ident.to_string() == "fn"));
+    match tokens.last_mut() {
+        Some(TokenTree::Group(body)) if is_fn && body.delimiter() == Delimiter::Brace => {
+            let mut errors = Vec::new();
+            let stream = rewrite(body.stream(), &wrapper, &mut errors);
+            let mut new_body =
+                Group::new(Delimiter::Brace, errors.into_iter().chain(stream).collect());
+            new_body.set_span(body.span());
+            *body = new_body;
+        }
+        _ => {
+            return compile_error(
+                "`#[try_or_wrap_fn]` can only be applied to functions with a body",
+                Span::call_site(),
+            )
+            .into_iter()
+            .collect();
+        }
+    }
+    tokens.into_iter().collect()
+}
+
+/// Replaces every `expr??` in `stream` (recursively) by `(::try_or_wrap_s::try_or_wrap!(expr?, wrapper))`
+///
+/// `compile_error!` statements are pushed to `errors` for `??` that can't be rewritten.
+fn rewrite(stream: TokenStream, wrapper: &[TokenTree], errors: &mut Vec<TokenTree>) -> TokenStream {
+    let mut out: Vec<TokenTree> = Vec::new();
+    let mut iter = stream.into_iter().peekable();
+    while let Some(tt) = iter.next() {
+        match tt {
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), rewrite(group.stream(), wrapper, errors));
+                new_group.set_span(group.span());
+                out.push(TokenTree::Group(new_group));
+            }
+            TokenTree::Punct(ref question_mark)
+                if question_mark.as_char() == '?'
+                    && matches!(iter.peek(), Some(TokenTree::Punct(next)) if next.as_char() == '?') =>
+            {
+                let second = iter.next().unwrap();
+                let span = question_mark.span();
+                let start = expression_start(&out);
+                if start == out.len() {
+                    errors.extend(compile_error(
+                        "expected a method call chain before `??`, try wrapping the expression in parentheses",
+                        span,
+                    ));
+                    errors.push(punct(';', Spacing::Alone, span));
+                    out.push(tt);
+                    out.push(second);
+                    continue;
+                }
+                let mut inner: Vec<TokenTree> = out.drain(start..).collect();
+                inner.push(TokenTree::Punct(Punct::new('?', Spacing::Alone)));
+                if !wrapper.is_empty() {
+                    inner.push(TokenTree::Punct(Punct::new(',', Spacing::Alone)));
+                    inner.extend(wrapper.iter().cloned());
+                }
+                // A proc macro has no `$crate`: this breaks if the dependency is renamed
+                let mut invocation = vec![
+                    punct(':', Spacing::Joint, span),
+                    punct(':', Spacing::Alone, span),
+                    TokenTree::Ident(Ident::new("try_or_wrap_s", span)),
+                    punct(':', Spacing::Joint, span),
+                    punct(':', Spacing::Alone, span),
+                    TokenTree::Ident(Ident::new("try_or_wrap", span)),
+                    punct('!', Spacing::Alone, span),
+                ];
+                invocation.push(group(
+                    Delimiter::Parenthesis,
+                    inner.into_iter().collect(),
+                    span,
+                ));
+                // Parenthesized so that it is a single token tree for subsequent `??` in the same chain
+                out.push(group(
+                    Delimiter::Parenthesis,
+                    invocation.into_iter().collect(),
+                    span,
+                ));
+            }
+            other => out.push(other),
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Index in `tokens` of the start of the postfix expression chain that ends `tokens`
+fn expression_start(tokens: &[TokenTree]) -> usize {
+    let mut i = tokens.len();
+    while i > 0 {
+        match &tokens[i - 1] {
+            TokenTree::Punct(p) if p.as_char() == '?' => i -= 1,
+            TokenTree::Group(g) if g.delimiter() != Delimiter::Brace => {
+                i -= 1;
+                // Macro invocation: `path!(...)`
+                if i >= 2 && is_punct(&tokens[i - 1], '!') {
+                    match &tokens[i - 2] {
+                        TokenTree::Ident(ident) if !is_keyword(&ident.to_string()) => i -= 1,
+                        _ => {}
+                    }
+                }
+            }
+            TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                let after_dot = i >= 2 && is_punct(&tokens[i - 2], '.');
+                if is_keyword(&name) && !(name == "await" && after_dot) {
+                    break;
+                }
+                i -= 1;
+                match continuation(tokens, i) {
+                    Some(len) => i -= len,
+                    None => break,
+                }
+            }
+            TokenTree::Literal(_) => {
+                i -= 1;
+                match continuation(tokens, i) {
+                    Some(len) => i -= len,
+                    None => break,
+                }
+            }
+            // Turbofish: `::<...>`
+            TokenTree::Punct(p)
+                if p.as_char() == '>' && !(i >= 2 && is_punct(&tokens[i - 2], '-')) =>
+            {
+                match matching_angle_bracket(tokens, i - 1) {
+                    Some(open) if open >= 2 && is_path_separator(tokens, open) => i = open - 2,
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+    }
+    // e.g. `async { ... }.await` or `a..b`: the chain doesn't start with an operand
+    if i < tokens.len() && is_punct(&tokens[i], '.') {
+        return tokens.len();
+    }
+    i
+}
+
+/// Length of the `.` or `::` that precedes `tokens[i]`, if any
+fn continuation(tokens: &[TokenTree], i: usize) -> Option<usize> {
+    if i >= 1 && is_punct(&tokens[i - 1], '.') {
+        Some(1)
+    } else if is_path_separator(tokens, i) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_path_separator(tokens: &[TokenTree], i: usize) -> bool {
+    i >= 2
+        && is_punct(&tokens[i - 1], ':')
+        && matches!(&tokens[i - 2], TokenTree::Punct(p) if p.as_char() == ':' && p.spacing() == Spacing::Joint)
+}
+
+fn matching_angle_bracket(tokens: &[TokenTree], close: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in (0..=close).rev() {
+        if let TokenTree::Punct(p) = &tokens[i] {
+            match p.as_char() {
+                '>' if !(i >= 1 && is_punct(&tokens[i - 1], '-')) => depth += 1,
+                '<' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+fn is_punct(tt: &TokenTree, c: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+fn is_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "as" | "async"
+            | "await"
+            | "box"
+            | "break"
+            | "const"
+            | "continue"
+            | "do"
+            | "dyn"
+            | "else"
+            | "enum"
+            | "extern"
+            | "fn"
+            | "for"
+            | "if"
+            | "impl"
+            | "in"
+            | "let"
+            | "loop"
+            | "match"
+            | "mod"
+            | "move"
+            | "mut"
+            | "pub"
+            | "ref"
+            | "return"
+            | "static"
+            | "struct"
+            | "trait"
+            | "try"
+            | "type"
+            | "unsafe"
+            | "use"
+            | "where"
+            | "while"
+            | "yield"
+    )
+}
+
+fn punct(c: char, spacing: Spacing, span: Span) -> TokenTree {
+    let mut punct = Punct::new(c, spacing);
+    punct.set_span(span);
+    TokenTree::Punct(punct)
+}
+
+fn group(delimiter: Delimiter, stream: TokenStream, span: Span) -> TokenTree {
+    let mut group = Group::new(delimiter, stream);
+    group.set_span(span);
+    TokenTree::Group(group)
+}
+
+fn compile_error(message: &str, span: Span) -> Vec<TokenTree> {
+    let mut message = Literal::string(message);
+    message.set_span(span);
+    vec![
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        punct('!', Spacing::Alone, span),
+        group(
+            Delimiter::Parenthesis,
+            TokenTree::Literal(message).into(),
+            span,
+        ),
+    ]
+}